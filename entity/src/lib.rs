@@ -2,5 +2,19 @@
 
 pub mod prelude;
 
+pub mod api_token;
+pub mod authorization_code;
+pub mod comment;
+pub mod file;
+pub mod follower;
 pub mod page;
+pub mod page_revision;
+pub mod page_tag;
+pub mod post_draft;
+pub mod preview_link;
+pub mod sea_orm_active_enums;
+pub mod session;
 pub mod settings;
+pub mod tag;
+pub mod user;
+pub mod webmention;
@@ -18,9 +18,86 @@ pub struct Model {
     pub content_html: String,
     pub is_post: bool,
     pub is_published: bool,
+    pub menu_order: Option<i32>,
+    pub author_id: Option<i32>,
+    pub announced: Option<DateTime>,
+    pub modified: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::comment::Entity")]
+    Comment,
+    #[sea_orm(has_many = "super::page_revision::Entity")]
+    PageRevision,
+    #[sea_orm(has_many = "super::page_tag::Entity")]
+    PageTag,
+    #[sea_orm(has_many = "super::post_draft::Entity")]
+    PostDraft,
+    #[sea_orm(has_many = "super::preview_link::Entity")]
+    PreviewLink,
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::AuthorId",
+        to = "super::user::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    User,
+    #[sea_orm(has_many = "super::webmention::Entity")]
+    Webmention,
+}
+
+impl Related<super::comment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Comment.def()
+    }
+}
+
+impl Related<super::page_revision::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PageRevision.def()
+    }
+}
+
+impl Related<super::page_tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PageTag.def()
+    }
+}
+
+impl Related<super::post_draft::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostDraft.def()
+    }
+}
+
+impl Related<super::preview_link::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PreviewLink.def()
+    }
+}
+
+impl Related<super::tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::page_tag::Relation::Tag.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::page_tag::Relation::Page.def().rev())
+    }
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::webmention::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Webmention.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -1,4 +1,17 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.10.5
 
+pub use super::api_token::Entity as ApiToken;
+pub use super::authorization_code::Entity as AuthorizationCode;
+pub use super::comment::Entity as Comment;
+pub use super::file::Entity as File;
+pub use super::follower::Entity as Follower;
 pub use super::page::Entity as Page;
+pub use super::page_revision::Entity as PageRevision;
+pub use super::page_tag::Entity as PageTag;
+pub use super::post_draft::Entity as PostDraft;
+pub use super::preview_link::Entity as PreviewLink;
+pub use super::session::Entity as Session;
 pub use super::settings::Entity as Settings;
+pub use super::tag::Entity as Tag;
+pub use super::user::Entity as User;
+pub use super::webmention::Entity as Webmention;
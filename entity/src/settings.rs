@@ -20,6 +20,17 @@ pub struct Model {
     #[sea_orm(column_type = "Text")]
     pub javascript: String,
     pub posts_per_page: i32,
+    #[sea_orm(column_type = "Text")]
+    pub site_title: String,
+    #[sea_orm(column_type = "Text")]
+    pub site_url: String,
+    #[sea_orm(column_type = "Text")]
+    pub robots_txt: String,
+    pub spam_min_fill_seconds: i32,
+    pub spam_max_submissions_per_hour: i32,
+    pub spam_score_threshold: i32,
+    #[sea_orm(column_type = "Text")]
+    pub actor_private_key: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -2,6 +2,25 @@
 // Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
 mod m20230101_000001_create_tables;
+mod m20230102_000001_add_page_menu_order;
+mod m20230103_000001_create_file_table;
+mod m20230104_000001_create_user_tables;
+mod m20230105_000001_add_user_roles;
+mod m20230106_000001_add_site_settings;
+mod m20230107_000001_add_robots_txt_setting;
+mod m20230108_000001_create_tag_tables;
+mod m20230109_000001_create_search_index;
+mod m20230110_000001_create_page_revision_table;
+mod m20230111_000001_create_post_draft_table;
+mod m20230112_000001_create_preview_link_table;
+mod m20230113_000001_create_api_token_table;
+mod m20230114_000001_create_authorization_code_table;
+mod m20230115_000001_create_webmention_table;
+mod m20230116_000001_create_comment_table;
+mod m20230117_000001_add_spam_settings;
+mod m20230118_000001_create_follower_table;
+mod m20230119_000001_add_page_announced;
+mod m20230120_000001_add_page_modified;
 
 pub use sea_orm_migration::prelude::*;
 
@@ -10,6 +29,27 @@ pub struct Migrator;
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20230101_000001_create_tables::Migration)]
+        vec![
+            Box::new(m20230101_000001_create_tables::Migration),
+            Box::new(m20230102_000001_add_page_menu_order::Migration),
+            Box::new(m20230103_000001_create_file_table::Migration),
+            Box::new(m20230104_000001_create_user_tables::Migration),
+            Box::new(m20230105_000001_add_user_roles::Migration),
+            Box::new(m20230106_000001_add_site_settings::Migration),
+            Box::new(m20230107_000001_add_robots_txt_setting::Migration),
+            Box::new(m20230108_000001_create_tag_tables::Migration),
+            Box::new(m20230109_000001_create_search_index::Migration),
+            Box::new(m20230110_000001_create_page_revision_table::Migration),
+            Box::new(m20230111_000001_create_post_draft_table::Migration),
+            Box::new(m20230112_000001_create_preview_link_table::Migration),
+            Box::new(m20230113_000001_create_api_token_table::Migration),
+            Box::new(m20230114_000001_create_authorization_code_table::Migration),
+            Box::new(m20230115_000001_create_webmention_table::Migration),
+            Box::new(m20230116_000001_create_comment_table::Migration),
+            Box::new(m20230117_000001_add_spam_settings::Migration),
+            Box::new(m20230118_000001_create_follower_table::Migration),
+            Box::new(m20230119_000001_add_page_announced::Migration),
+            Box::new(m20230120_000001_add_page_modified::Migration),
+        ]
     }
 }
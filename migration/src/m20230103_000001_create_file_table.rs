@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(File::Table)
+                    .col(
+                        ColumnDef::new(File::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(File::Time).timestamp().not_null())
+                    .col(ColumnDef::new(File::Name).text().not_null())
+                    .col(ColumnDef::new(File::MimeType).text().not_null())
+                    .col(ColumnDef::new(File::Size).big_integer().not_null())
+                    .col(ColumnDef::new(File::Content).binary().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-file-name")
+                    .table(File::Table)
+                    .col(File::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(File::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum File {
+    Table,
+    Id,
+    Time,
+    Name,
+    MimeType,
+    Size,
+    Content,
+}
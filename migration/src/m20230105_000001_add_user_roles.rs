@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{ConnectionTrait, DatabaseBackend, Statement},
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Accounts created before roles existed had full access,
+        // so they become admins.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(
+                        ColumnDef::new(User::Role)
+                            .text()
+                            .not_null()
+                            .default("admin"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        match manager.get_database_backend() {
+            DatabaseBackend::Sqlite => {
+                // SQLite does not support adding foreign key constraints
+                // to existing tables, but a new column may reference another table.
+                manager
+                    .get_connection()
+                    .execute(Statement::from_string(
+                        DatabaseBackend::Sqlite,
+                        r#"ALTER TABLE "page" ADD COLUMN "author_id" integer REFERENCES "user" ("id") ON DELETE SET NULL"#
+                            .to_owned(),
+                    ))
+                    .await?;
+            }
+            _ => {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Page::Table)
+                            .add_column(ColumnDef::new(Page::AuthorId).integer())
+                            .to_owned(),
+                    )
+                    .await?;
+
+                manager
+                    .create_foreign_key(
+                        ForeignKey::create()
+                            .name("fk-page-author_id")
+                            .from(Page::Table, Page::AuthorId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DatabaseBackend::Sqlite {
+            manager
+                .drop_foreign_key(
+                    ForeignKey::drop()
+                        .name("fk-page-author_id")
+                        .table(Page::Table)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Page::Table)
+                    .drop_column(Page::AuthorId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::Role)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    Id,
+    Role,
+}
+
+#[derive(Iden)]
+enum Page {
+    Table,
+    AuthorId,
+}
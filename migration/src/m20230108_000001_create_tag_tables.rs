@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Tag::Table)
+                    .col(
+                        ColumnDef::new(Tag::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Tag::Name).text().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-tag-name")
+                    .table(Tag::Table)
+                    .col(Tag::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PageTag::Table)
+                    .col(ColumnDef::new(PageTag::PageId).integer().not_null())
+                    .col(ColumnDef::new(PageTag::TagId).integer().not_null())
+                    .primary_key(Index::create().col(PageTag::PageId).col(PageTag::TagId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-page_tag-page_id")
+                            .from(PageTag::Table, PageTag::PageId)
+                            .to(Page::Table, Page::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-page_tag-tag_id")
+                            .from(PageTag::Table, PageTag::TagId)
+                            .to(Tag::Table, Tag::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-page_tag-tag_id")
+                    .table(PageTag::Table)
+                    .col(PageTag::TagId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PageTag::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Tag::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum Page {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum Tag {
+    Table,
+    Id,
+    Name,
+}
+
+#[derive(Iden)]
+enum PageTag {
+    Table,
+    PageId,
+    TagId,
+}
@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{ConnectionTrait, DatabaseBackend, Statement},
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Each backend has its own full-text search facilities, which cannot be expressed
+// using `sea_query`. The queries in `src/search.rs` must match these structures.
+const POSTGRES_UP: &[&str] = &[
+    r#"CREATE INDEX "idx-page-search" ON "page" USING GIN (to_tsvector('english', "title" || ' ' || "content_markdown"))"#,
+];
+
+const POSTGRES_DOWN: &[&str] = &[r#"DROP INDEX "idx-page-search""#];
+
+// An external content FTS5 table, kept in sync with the `page` table by triggers.
+// See https://www.sqlite.org/fts5.html#external_content_tables
+const SQLITE_UP: &[&str] = &[
+    r#"CREATE VIRTUAL TABLE "page_search" USING fts5("title", "content_markdown", content='page', content_rowid='id', tokenize='porter unicode61')"#,
+    r#"CREATE TRIGGER "page_search_insert" AFTER INSERT ON "page" BEGIN
+        INSERT INTO "page_search" ("rowid", "title", "content_markdown") VALUES (new."id", new."title", new."content_markdown");
+    END"#,
+    r#"CREATE TRIGGER "page_search_delete" AFTER DELETE ON "page" BEGIN
+        INSERT INTO "page_search" ("page_search", "rowid", "title", "content_markdown") VALUES ('delete', old."id", old."title", old."content_markdown");
+    END"#,
+    r#"CREATE TRIGGER "page_search_update" AFTER UPDATE OF "title", "content_markdown" ON "page" BEGIN
+        INSERT INTO "page_search" ("page_search", "rowid", "title", "content_markdown") VALUES ('delete', old."id", old."title", old."content_markdown");
+        INSERT INTO "page_search" ("rowid", "title", "content_markdown") VALUES (new."id", new."title", new."content_markdown");
+    END"#,
+    // Index pages that existed before this migration.
+    r#"INSERT INTO "page_search" ("page_search") VALUES ('rebuild')"#,
+];
+
+const SQLITE_DOWN: &[&str] = &[
+    r#"DROP TRIGGER "page_search_update""#,
+    r#"DROP TRIGGER "page_search_delete""#,
+    r#"DROP TRIGGER "page_search_insert""#,
+    r#"DROP TABLE "page_search""#,
+];
+
+async fn execute(manager: &SchemaManager<'_>, statements: &[&str]) -> Result<(), DbErr> {
+    let backend = manager.get_database_backend();
+
+    for statement in statements {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(backend, (*statement).to_owned()))
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        match manager.get_database_backend() {
+            DatabaseBackend::Postgres => execute(manager, POSTGRES_UP).await,
+            DatabaseBackend::Sqlite => execute(manager, SQLITE_UP).await,
+            DatabaseBackend::MySql => Err(DbErr::Migration(
+                "full-text search is not supported on MySQL".to_owned(),
+            )),
+        }
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        match manager.get_database_backend() {
+            DatabaseBackend::Postgres => execute(manager, POSTGRES_DOWN).await,
+            DatabaseBackend::Sqlite => execute(manager, SQLITE_DOWN).await,
+            DatabaseBackend::MySql => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use sea_orm_migration::{prelude::*, sea_orm::DatabaseBackend};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PageRevision::Table)
+                    .col(
+                        ColumnDef::new(PageRevision::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PageRevision::PageId).integer().not_null())
+                    .col(ColumnDef::new(PageRevision::Time).timestamp().not_null())
+                    .col(ColumnDef::new(PageRevision::Title).text().not_null())
+                    .col(
+                        ColumnDef::new(PageRevision::ContentMarkdown)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PageRevision::AuthorId).integer())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-page_revision-page_id")
+                            .from(PageRevision::Table, PageRevision::PageId)
+                            .to(Page::Table, Page::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-page_revision-author_id")
+                            .from(PageRevision::Table, PageRevision::AuthorId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-page_revision-page_id")
+                    .table(PageRevision::Table)
+                    .col(PageRevision::PageId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Record the current state of existing posts as their first revision,
+        // so that it can be restored after they are edited.
+        // Times are stored in UTC, while Postgres uses the server's time zone.
+        let now = match manager.get_database_backend() {
+            DatabaseBackend::Postgres => "CURRENT_TIMESTAMP AT TIME ZONE 'UTC'",
+            _ => "CURRENT_TIMESTAMP",
+        };
+
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(PageRevision::Table)
+                    .columns([
+                        PageRevision::PageId,
+                        PageRevision::Time,
+                        PageRevision::Title,
+                        PageRevision::ContentMarkdown,
+                        PageRevision::AuthorId,
+                    ])
+                    .select_from(
+                        Query::select()
+                            .column(Page::Id)
+                            .expr(Expr::cust(now))
+                            .column(Page::Title)
+                            .column(Page::ContentMarkdown)
+                            .column(Page::AuthorId)
+                            .from(Page::Table)
+                            .and_where(Expr::col(Page::IsPost).eq(true))
+                            .to_owned(),
+                    )
+                    .map_err(|error| DbErr::Migration(error.to_string()))?
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PageRevision::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Page {
+    Table,
+    Id,
+    Title,
+    ContentMarkdown,
+    IsPost,
+    AuthorId,
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum PageRevision {
+    Table,
+    Id,
+    PageId,
+    Time,
+    Title,
+    ContentMarkdown,
+    AuthorId,
+}
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PostDraft::Table)
+                    .col(
+                        ColumnDef::new(PostDraft::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    // `NULL` for drafts of posts that have never been saved.
+                    .col(ColumnDef::new(PostDraft::PageId).integer())
+                    .col(ColumnDef::new(PostDraft::UserId).integer().not_null())
+                    .col(ColumnDef::new(PostDraft::SaveTime).timestamp().not_null())
+                    .col(ColumnDef::new(PostDraft::Title).text().not_null())
+                    .col(ColumnDef::new(PostDraft::Url).text().not_null())
+                    .col(ColumnDef::new(PostDraft::Date).text().not_null())
+                    .col(ColumnDef::new(PostDraft::Time).text().not_null())
+                    .col(ColumnDef::new(PostDraft::Tags).text().not_null())
+                    .col(ColumnDef::new(PostDraft::Content).text().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-post_draft-page_id")
+                            .from(PostDraft::Table, PostDraft::PageId)
+                            .to(Page::Table, Page::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-post_draft-user_id")
+                            .from(PostDraft::Table, PostDraft::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-post_draft-user_id-page_id")
+                    .table(PostDraft::Table)
+                    .col(PostDraft::UserId)
+                    .col(PostDraft::PageId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PostDraft::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Page {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum PostDraft {
+    Table,
+    Id,
+    PageId,
+    UserId,
+    SaveTime,
+    Title,
+    Url,
+    Date,
+    Time,
+    Tags,
+    Content,
+}
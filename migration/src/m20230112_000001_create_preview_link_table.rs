@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PreviewLink::Table)
+                    .col(
+                        ColumnDef::new(PreviewLink::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PreviewLink::PageId).integer().not_null())
+                    .col(ColumnDef::new(PreviewLink::Token).text().not_null())
+                    .col(ColumnDef::new(PreviewLink::Created).timestamp().not_null())
+                    // `NULL` for links that never expire.
+                    .col(ColumnDef::new(PreviewLink::Expires).timestamp())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-preview_link-page_id")
+                            .from(PreviewLink::Table, PreviewLink::PageId)
+                            .to(Page::Table, Page::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-preview_link-token")
+                    .table(PreviewLink::Table)
+                    .col(PreviewLink::Token)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PreviewLink::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Page {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum PreviewLink {
+    Table,
+    Id,
+    PageId,
+    Token,
+    Created,
+    Expires,
+}
@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ApiToken::Table)
+                    .col(
+                        ColumnDef::new(ApiToken::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ApiToken::UserId).integer().not_null())
+                    .col(ColumnDef::new(ApiToken::Name).text().not_null())
+                    // Only the SHA-256 hash of the token is stored,
+                    // so a leaked database does not grant API access.
+                    .col(ColumnDef::new(ApiToken::TokenHash).text().not_null())
+                    .col(ColumnDef::new(ApiToken::Scope).text().not_null())
+                    .col(ColumnDef::new(ApiToken::Created).timestamp().not_null())
+                    // `NULL` for tokens that have never been used.
+                    .col(ColumnDef::new(ApiToken::LastUsed).timestamp())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-api_token-user_id")
+                            .from(ApiToken::Table, ApiToken::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-api_token-token_hash")
+                    .table(ApiToken::Table)
+                    .col(ApiToken::TokenHash)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiToken::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum ApiToken {
+    Table,
+    Id,
+    UserId,
+    Name,
+    TokenHash,
+    Scope,
+    Created,
+    LastUsed,
+}
@@ -0,0 +1,102 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuthorizationCode::Table)
+                    .col(
+                        ColumnDef::new(AuthorizationCode::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AuthorizationCode::UserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    // Like API tokens, codes are stored as SHA-256 hashes.
+                    .col(
+                        ColumnDef::new(AuthorizationCode::CodeHash)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AuthorizationCode::ClientId)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AuthorizationCode::RedirectUri)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AuthorizationCode::CodeChallenge)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(AuthorizationCode::Scope).text().not_null())
+                    .col(
+                        ColumnDef::new(AuthorizationCode::Expires)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-authorization_code-user_id")
+                            .from(AuthorizationCode::Table, AuthorizationCode::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-authorization_code-code_hash")
+                    .table(AuthorizationCode::Table)
+                    .col(AuthorizationCode::CodeHash)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuthorizationCode::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum AuthorizationCode {
+    Table,
+    Id,
+    UserId,
+    CodeHash,
+    ClientId,
+    RedirectUri,
+    CodeChallenge,
+    Scope,
+    Expires,
+}
@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Webmention::Table)
+                    .col(
+                        ColumnDef::new(Webmention::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Webmention::PageId).integer().not_null())
+                    .col(ColumnDef::new(Webmention::Source).text().not_null())
+                    // The title of the source page, or an empty string if it has none.
+                    .col(ColumnDef::new(Webmention::Title).text().not_null())
+                    .col(ColumnDef::new(Webmention::Received).timestamp().not_null())
+                    .col(
+                        ColumnDef::new(Webmention::IsApproved)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-webmention-page_id")
+                            .from(Webmention::Table, Webmention::PageId)
+                            .to(Page::Table, Page::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // A source mentioning the same post again updates the existing mention.
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-webmention-page_id-source")
+                    .table(Webmention::Table)
+                    .col(Webmention::PageId)
+                    .col(Webmention::Source)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Webmention::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Page {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum Webmention {
+    Table,
+    Id,
+    PageId,
+    Source,
+    Title,
+    Received,
+    IsApproved,
+}
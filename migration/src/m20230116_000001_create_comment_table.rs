@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Comment::Table)
+                    .col(
+                        ColumnDef::new(Comment::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Comment::PageId).integer().not_null())
+                    .col(ColumnDef::new(Comment::Time).timestamp().not_null())
+                    .col(ColumnDef::new(Comment::AuthorName).text().not_null())
+                    // An empty string if the author did not give a website.
+                    .col(ColumnDef::new(Comment::AuthorUrl).text().not_null())
+                    .col(ColumnDef::new(Comment::ContentMarkdown).text().not_null())
+                    .col(ColumnDef::new(Comment::ContentHtml).text().not_null())
+                    .col(
+                        ColumnDef::new(Comment::Status)
+                            .text()
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-comment-page_id")
+                            .from(Comment::Table, Comment::PageId)
+                            .to(Page::Table, Page::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-comment-page_id-status")
+                    .table(Comment::Table)
+                    .col(Comment::PageId)
+                    .col(Comment::Status)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Comment::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Page {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum Comment {
+    Table,
+    Id,
+    PageId,
+    Time,
+    AuthorName,
+    AuthorUrl,
+    ContentMarkdown,
+    ContentHtml,
+    Status,
+}
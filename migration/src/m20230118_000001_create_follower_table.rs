@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Follower::Table)
+                    .col(
+                        ColumnDef::new(Follower::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    // The ActivityPub ID of the following actor.
+                    .col(ColumnDef::new(Follower::Actor).text().not_null())
+                    // The shared inbox of the actor's server if it has one,
+                    // so each post is delivered only once per server.
+                    .col(ColumnDef::new(Follower::Inbox).text().not_null())
+                    .col(ColumnDef::new(Follower::Followed).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-follower-actor")
+                    .table(Follower::Table)
+                    .col(Follower::Actor)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // The key pair of the site's ActivityPub actor, used to sign outgoing requests.
+        // It is generated when the server starts for the first time after this migration.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .add_column(
+                        ColumnDef::new(Settings::ActorPrivateKey)
+                            .text()
+                            .not_null()
+                            .default(""),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .drop_column(Settings::ActorPrivateKey)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Follower::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Follower {
+    Table,
+    Id,
+    Actor,
+    Inbox,
+    Followed,
+}
+
+#[derive(Iden)]
+enum Settings {
+    Table,
+    ActorPrivateKey,
+}
@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use chrono::Utc;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // When other sites were notified of the post, via Webmention and ActivityPub.
+        // Scheduled posts are announced once their time arrives.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Page::Table)
+                    .add_column(ColumnDef::new(Page::Announced).timestamp())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Published posts have already been announced when they were published,
+        // unless they are scheduled for the future, in which case they still need to be.
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Page::Table)
+                    .value(Page::Announced, Expr::col(Page::Time))
+                    .and_where(Expr::col(Page::IsPost).eq(true))
+                    .and_where(Expr::col(Page::IsPublished).eq(true))
+                    .and_where(Expr::col(Page::Time).lte(Utc::now().naive_utc()))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Page::Table)
+                    .drop_column(Page::Announced)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Page {
+    Table,
+    Time,
+    IsPost,
+    IsPublished,
+    Announced,
+}
@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // When the post was last saved. Unlike revisions, which are only recorded
+        // when the title or content changes, this covers every change.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Page::Table)
+                    .add_column(ColumnDef::new(Page::Modified).timestamp())
+                    .to_owned(),
+            )
+            .await?;
+
+        // The latest revision is the best approximation for existing posts.
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Page::Table)
+                    .value(
+                        Page::Modified,
+                        Expr::cust(
+                            "(SELECT MAX(page_revision.time) FROM page_revision \
+                            WHERE page_revision.page_id = page.id)",
+                        ),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Page::Table)
+                    .drop_column(Page::Modified)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Page {
+    Table,
+    Modified,
+}
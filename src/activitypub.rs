@@ -0,0 +1,1659 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+//! ActivityPub (https://www.w3.org/TR/activitypub/) support. The site is a single
+//! actor that can be followed from Mastodon and other fediverse software,
+//! and published posts are delivered to its followers. Requests between servers
+//! are authenticated using HTTP signatures
+//! (https://datatracker.ietf.org/doc/html/draft-cavage-http-signatures-12).
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query},
+    http::{
+        header::{CONTENT_TYPE, DATE, HOST},
+        HeaderMap, Method, StatusCode, Uri,
+    },
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use entity::{follower, page, prelude::Follower};
+use regex::Regex;
+use reqwest::{Client, RequestBuilder, Url};
+use rsa::{
+    pkcs1::DecodeRsaPublicKey,
+    pkcs1v15::{Signature, SigningKey, VerifyingKey},
+    pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding},
+    signature::{SignatureEncoding, Signer, Verifier},
+    RsaPrivateKey, RsaPublicKey,
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, Set,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    admin::is_valid_site_url,
+    random_token, settings,
+    site::published_pages,
+    webmention::{http_client, response_text, send},
+    ErrorResponse,
+};
+
+/// The username of the actor, as in `@blog@example.com`.
+const ACTOR_NAME: &str = "blog";
+
+const ACTOR_URL: &str = "/activitypub/actor";
+
+const INBOX_URL: &str = "/activitypub/inbox";
+
+const OUTBOX_URL: &str = "/activitypub/outbox";
+
+const FOLLOWERS_URL: &str = "/activitypub/followers";
+
+const POSTS_URL: &str = "/activitypub/posts";
+
+const ACTIVITY_JSON: &str = "application/activity+json";
+
+const ACTIVITY_STREAMS_CONTEXT: &str = "https://www.w3.org/ns/activitystreams";
+
+const PUBLIC_COLLECTION: &str = "https://www.w3.org/ns/activitystreams#Public";
+
+const KEY_BITS: usize = 2048;
+
+/// Signed requests whose `Date` header is further from
+/// the current time than this are rejected, to prevent replays.
+const MAX_CLOCK_SKEW_SECONDS: i64 = 12 * 60 * 60;
+
+/// How long public keys of other actors are cached before they are fetched again.
+const PUBLIC_KEY_CACHE_SECONDS: u64 = 24 * 60 * 60;
+
+const MAX_CACHED_PUBLIC_KEYS: usize = 1000;
+
+pub(super) fn router() -> Router {
+    Router::new()
+        .route("/.well-known/webfinger", get(get_webfinger))
+        .route(ACTOR_URL, get(get_actor))
+        .route(INBOX_URL, post(post_inbox))
+        .route(OUTBOX_URL, get(get_outbox))
+        .route(FOLLOWERS_URL, get(get_followers))
+        .route(&format!("{}/:post_id", POSTS_URL), get(get_post))
+}
+
+/// A JSON response with the ActivityPub media type.
+struct ActivityJson(Value);
+
+impl IntoResponse for ActivityJson {
+    fn into_response(self) -> Response {
+        ([(CONTENT_TYPE, ACTIVITY_JSON)], Json(self.0)).into_response()
+    }
+}
+
+/// Generates the actor's key pair if there is none yet. This takes a while,
+/// so it happens when the server starts rather than when the key is first needed.
+pub(crate) async fn generate_actor_key(
+    connection: &DatabaseConnection,
+) -> Result<(), ErrorResponse> {
+    let settings = settings(connection).await?;
+
+    if !settings.actor_private_key.is_empty() {
+        return Ok(());
+    }
+
+    let error = (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "unable to generate ActivityPub key",
+    );
+
+    let private_key =
+        tokio::task::spawn_blocking(|| RsaPrivateKey::new(&mut rand::thread_rng(), KEY_BITS))
+            .await
+            .map_err(|_| error)?
+            .map_err(|_| error)?;
+
+    let mut settings: settings::ActiveModel = settings.into();
+
+    settings.actor_private_key = Set(private_key
+        .to_pkcs8_pem(LineEnding::LF)
+        .map_err(|_| error)?
+        .to_string());
+
+    settings
+        .update(connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save settings"))?;
+
+    Ok(())
+}
+
+struct CachedPublicKey {
+    owner: String,
+    public_key: RsaPublicKey,
+    fetched: Instant,
+}
+
+/// Public keys of other actors, by key ID, so that verifying a signature
+/// doesn't require a request to the signer's server every time.
+/// A single instance is shared between all requests.
+#[derive(Clone, Default)]
+pub(crate) struct PublicKeyCache {
+    public_keys: Arc<Mutex<HashMap<String, CachedPublicKey>>>,
+}
+
+impl PublicKeyCache {
+    pub(crate) fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the owner and the public key with the ID `key_id`, if they are cached.
+    fn get(&self, key_id: &str) -> Option<(String, RsaPublicKey)> {
+        let public_keys = self.public_keys.lock().unwrap();
+
+        public_keys
+            .get(key_id)
+            .filter(|cached| {
+                cached.fetched.elapsed() < Duration::from_secs(PUBLIC_KEY_CACHE_SECONDS)
+            })
+            .map(|cached| (cached.owner.clone(), cached.public_key.clone()))
+    }
+
+    fn insert(&self, key_id: &str, owner: &str, public_key: &RsaPublicKey) {
+        let mut public_keys = self.public_keys.lock().unwrap();
+
+        // Forget old keys so memory use stays bounded.
+        if public_keys.len() >= MAX_CACHED_PUBLIC_KEYS {
+            public_keys.retain(|_, cached| {
+                cached.fetched.elapsed() < Duration::from_secs(PUBLIC_KEY_CACHE_SECONDS)
+            });
+
+            if public_keys.len() >= MAX_CACHED_PUBLIC_KEYS {
+                public_keys.clear();
+            }
+        }
+
+        public_keys.insert(
+            key_id.to_owned(),
+            CachedPublicKey {
+                owner: owner.to_owned(),
+                public_key: public_key.clone(),
+                fetched: Instant::now(),
+            },
+        );
+    }
+}
+
+/// The credentials used to sign requests on behalf of the actor.
+#[derive(Clone)]
+struct ActorKey {
+    key_id: String,
+    private_key: RsaPrivateKey,
+}
+
+impl ActorKey {
+    fn new(settings: &settings::Model, base_url: &str) -> Result<Self, ErrorResponse> {
+        Ok(ActorKey {
+            key_id: format!("{}#main-key", actor_url(base_url)),
+            private_key: RsaPrivateKey::from_pkcs8_pem(&settings.actor_private_key)
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "invalid ActivityPub key"))?,
+        })
+    }
+}
+
+/// Returns the configured site URL. Other servers store the IDs of the actor
+/// and its objects, so they can't be derived from the request's host,
+/// which might not be the site's public address.
+fn site_base_url(settings: &settings::Model) -> Result<&str, ErrorResponse> {
+    if settings.site_url.is_empty() {
+        Err((
+            StatusCode::NOT_FOUND,
+            "ActivityPub is not available until the site URL is configured",
+        ))
+    } else {
+        Ok(&settings.site_url)
+    }
+}
+
+fn actor_url(base_url: &str) -> String {
+    format!("{}{}", base_url, ACTOR_URL)
+}
+
+/// Returns the ID of an object that may be given either by its ID or embedded.
+fn object_id(value: &Value) -> Option<&str> {
+    value.as_str().or_else(|| value["id"].as_str())
+}
+
+/// Returns the ActivityPub representation of `post`.
+fn post_object(base_url: &str, post: &page::Model) -> Value {
+    json!({
+        "id": format!("{}{}/{}", base_url, POSTS_URL, post.id),
+        "type": "Article",
+        "attributedTo": actor_url(base_url),
+        "name": post.title,
+        "content": post.content_html,
+        "url": format!("{}/{}", base_url, post.url),
+        "published": post.time.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        "to": [PUBLIC_COLLECTION],
+        "cc": [format!("{}{}", base_url, FOLLOWERS_URL)],
+    })
+}
+
+/// Returns the activity announcing that `post` was published.
+fn create_activity(base_url: &str, post: &page::Model) -> Value {
+    let object = post_object(base_url, post);
+
+    json!({
+        "@context": ACTIVITY_STREAMS_CONTEXT,
+        "id": format!("{}#create", object["id"].as_str().unwrap_or_default()),
+        "type": "Create",
+        "actor": actor_url(base_url),
+        "published": object["published"],
+        "to": object["to"],
+        "cc": object["cc"],
+        "object": object,
+    })
+}
+
+/// Returns the domain part of the actor's handle, including the port if it isn't the default.
+fn domain(base_url: &str) -> String {
+    Url::parse(base_url).map_or_else(
+        |_| String::new(),
+        |url| match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_owned(),
+        },
+    )
+}
+
+#[derive(Deserialize)]
+struct WebfingerQuery {
+    resource: String,
+}
+
+/// Lets other servers find the actor from its handle.
+async fn get_webfinger(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Query(webfinger_query): Query<WebfingerQuery>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let settings = settings(database_connection).await?;
+    let base_url = site_base_url(&settings)?;
+    let actor_url = actor_url(base_url);
+    let subject = format!("acct:{}@{}", ACTOR_NAME, domain(base_url));
+    let profile_url = format!("{}/", base_url);
+
+    if ![&subject, &actor_url, &profile_url].contains(&&webfinger_query.resource) {
+        return Err((StatusCode::NOT_FOUND, "resource not found"));
+    }
+
+    Ok((
+        [(CONTENT_TYPE, "application/jrd+json")],
+        Json(json!({
+            "subject": subject,
+            "aliases": [actor_url, profile_url],
+            "links": [
+                {
+                    "rel": "self",
+                    "type": ACTIVITY_JSON,
+                    "href": actor_url,
+                },
+                {
+                    "rel": "http://webfinger.net/rel/profile-page",
+                    "type": "text/html",
+                    "href": profile_url,
+                },
+            ],
+        })),
+    ))
+}
+
+async fn get_actor(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let settings = settings(database_connection).await?;
+    let base_url = site_base_url(&settings)?;
+    let actor_key = ActorKey::new(&settings, base_url)?;
+
+    let public_key_pem = actor_key
+        .private_key
+        .to_public_key()
+        .to_public_key_pem(LineEnding::LF)
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to encode public key",
+            )
+        })?;
+
+    Ok(ActivityJson(json!({
+        "@context": [ACTIVITY_STREAMS_CONTEXT, "https://w3id.org/security/v1"],
+        "id": actor_url(base_url),
+        "type": "Person",
+        "preferredUsername": ACTOR_NAME,
+        "name": settings.site_title,
+        "url": format!("{}/", base_url),
+        "inbox": format!("{}{}", base_url, INBOX_URL),
+        "outbox": format!("{}{}", base_url, OUTBOX_URL),
+        "followers": format!("{}{}", base_url, FOLLOWERS_URL),
+        "publicKey": {
+            "id": actor_key.key_id,
+            "owner": actor_url(base_url),
+            "publicKeyPem": public_key_pem,
+        },
+    })))
+}
+
+#[derive(Deserialize)]
+struct OutboxQuery {
+    page: Option<String>,
+}
+
+/// Posts are embedded with their full content, so the outbox is split into pages
+/// like the site's list of posts. Without a page number, only the number of posts
+/// and links to the first and last pages are returned.
+async fn get_outbox(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Query(outbox_query): Query<OutboxQuery>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let settings = settings(database_connection).await?;
+    let base_url = site_base_url(&settings)?;
+    let outbox_url = format!("{}{}", base_url, OUTBOX_URL);
+    let page_url = |page_number: u64| format!("{}?page={}", outbox_url, page_number);
+
+    let error = (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "unable to retrieve posts",
+    );
+
+    let paginator = published_pages()
+        .filter(page::Column::IsPost.eq(true))
+        .order_by_desc(page::Column::Time)
+        .paginate(database_connection, settings.posts_per_page.max(1) as u64);
+
+    let counts = paginator.num_items_and_pages().await.map_err(|_| error)?;
+
+    // The first page always exists, even if there are no posts to show on it.
+    let number_of_pages = counts.number_of_pages.max(1);
+
+    let page_number = match outbox_query.page {
+        Some(page_number) => match page_number.parse() {
+            Ok(page_number) if page_number > 0 && page_number <= number_of_pages => page_number,
+            _ => return Err((StatusCode::NOT_FOUND, "page not found")),
+        },
+        None => {
+            return Ok(ActivityJson(json!({
+                "@context": ACTIVITY_STREAMS_CONTEXT,
+                "id": outbox_url,
+                "type": "OrderedCollection",
+                "totalItems": counts.number_of_items,
+                "first": page_url(1),
+                "last": page_url(number_of_pages),
+            })));
+        }
+    };
+
+    let posts = paginator
+        .fetch_page(page_number - 1)
+        .await
+        .map_err(|_| error)?;
+
+    let mut collection_page = json!({
+        "@context": ACTIVITY_STREAMS_CONTEXT,
+        "id": page_url(page_number),
+        "type": "OrderedCollectionPage",
+        "partOf": outbox_url,
+        "orderedItems": posts
+            .iter()
+            .map(|post| create_activity(base_url, post))
+            .collect::<Vec<_>>(),
+    });
+
+    if page_number > 1 {
+        collection_page["prev"] = json!(page_url(page_number - 1));
+    }
+
+    if page_number < number_of_pages {
+        collection_page["next"] = json!(page_url(page_number + 1));
+    }
+
+    Ok(ActivityJson(collection_page))
+}
+
+/// Only the number of followers is public, not who they are.
+async fn get_followers(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let settings = settings(database_connection).await?;
+    let base_url = site_base_url(&settings)?;
+
+    let count = Follower::find()
+        .count(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve followers",
+            )
+        })?;
+
+    Ok(ActivityJson(json!({
+        "@context": ACTIVITY_STREAMS_CONTEXT,
+        "id": format!("{}{}", base_url, FOLLOWERS_URL),
+        "type": "OrderedCollection",
+        "totalItems": count,
+    })))
+}
+
+async fn get_post(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(post_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let settings = settings(database_connection).await?;
+    let base_url = site_base_url(&settings)?;
+
+    let post = published_pages()
+        .filter(page::Column::IsPost.eq(true))
+        .filter(
+            page::Column::Id.eq(post_id
+                .parse::<i32>()
+                .map_err(|_| (StatusCode::BAD_REQUEST, "invalid post ID"))?),
+        )
+        .one(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve post"))?
+        .ok_or((StatusCode::NOT_FOUND, "post not found"))?;
+
+    let mut object = post_object(base_url, &post);
+
+    object["@context"] = json!(ACTIVITY_STREAMS_CONTEXT);
+
+    Ok(ActivityJson(object))
+}
+
+/// Returns a request that is signed by the actor. Only `POST` requests have a body,
+/// which is covered by the signature through its digest.
+fn signed_request(
+    client: &Client,
+    actor_key: &ActorKey,
+    method: Method,
+    url: &str,
+    body: Option<Vec<u8>>,
+) -> Option<RequestBuilder> {
+    let url = Url::parse(url).ok()?;
+
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str()?, port),
+        None => url.host_str()?.to_owned(),
+    };
+
+    let target = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_owned(),
+    };
+
+    let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+
+    let mut signed_headers = vec![
+        (
+            "(request-target)",
+            format!("{} {}", method.as_str().to_lowercase(), target),
+        ),
+        ("host", host.clone()),
+        ("date", date.clone()),
+    ];
+
+    let mut request = client
+        .request(method, url)
+        .header(HOST, host)
+        .header(DATE, date)
+        .header("Accept", ACTIVITY_JSON);
+
+    if let Some(body) = body {
+        let digest = format!("SHA-256={}", base64::encode(Sha256::digest(&body)));
+
+        signed_headers.push(("digest", digest.clone()));
+
+        request = request
+            .header("Digest", digest)
+            .header(CONTENT_TYPE, ACTIVITY_JSON)
+            .body(body);
+    }
+
+    let signing_string = signed_headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let signature = SigningKey::<Sha256>::new(actor_key.private_key.clone())
+        .sign(signing_string.as_bytes())
+        .to_bytes();
+
+    Some(request.header(
+        "Signature",
+        format!(
+            r#"keyId="{}",algorithm="rsa-sha256",headers="{}",signature="{}""#,
+            actor_key.key_id,
+            signed_headers
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(" "),
+            base64::encode(signature),
+        ),
+    ))
+}
+
+/// Fetches the ActivityPub object with the ID `url`. Requests are signed,
+/// because some servers only respond to signed requests.
+async fn fetch_object(client: &Client, actor_key: &ActorKey, url: &str) -> Option<Value> {
+    let response = send(signed_request(client, actor_key, Method::GET, url, None)?)
+        .await?
+        .error_for_status()
+        .ok()?;
+
+    serde_json::from_str(&response_text(response).await.ok()?).ok()
+}
+
+/// Posts `activity` to `inbox`, signed by the actor.
+async fn deliver(
+    client: &Client,
+    actor_key: &ActorKey,
+    inbox: &str,
+    activity: &Value,
+) -> Result<(), ErrorResponse> {
+    let error = (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "unable to deliver activity",
+    );
+
+    send(
+        signed_request(
+            client,
+            actor_key,
+            Method::POST,
+            inbox,
+            Some(serde_json::to_vec(activity).map_err(|_| error)?),
+        )
+        .ok_or(error)?,
+    )
+    .await
+    .ok_or(error)?
+    .error_for_status()
+    .map_err(|_| error)?;
+
+    Ok(())
+}
+
+/// Fetches the public key with the ID `key_id`, and returns it along with its owner.
+async fn fetch_public_key(
+    client: &Client,
+    actor_key: &ActorKey,
+    key_id: &str,
+) -> Result<(String, RsaPublicKey), ErrorResponse> {
+    let error = (
+        StatusCode::UNAUTHORIZED,
+        "missing or invalid HTTP signature",
+    );
+
+    let document = fetch_object(client, actor_key, key_id)
+        .await
+        .ok_or((StatusCode::UNAUTHORIZED, "unable to retrieve public key"))?;
+
+    // The key ID usually points to a fragment of the actor document.
+    let public_key = if document["publicKey"].is_object() {
+        &document["publicKey"]
+    } else {
+        &document
+    };
+
+    let owner = public_key["owner"].as_str().ok_or(error)?;
+
+    // Otherwise, anyone could publish a key claiming to belong to someone else.
+    let key_url = Url::parse(key_id).map_err(|_| error)?;
+    let owner_url = Url::parse(owner).map_err(|_| error)?;
+
+    if key_url.origin() != owner_url.origin() {
+        return Err(error);
+    }
+
+    let public_key_pem = public_key["publicKeyPem"].as_str().ok_or(error)?;
+
+    let public_key = RsaPublicKey::from_public_key_pem(public_key_pem)
+        .or_else(|_| RsaPublicKey::from_pkcs1_pem(public_key_pem))
+        .map_err(|_| error)?;
+
+    Ok((owner.to_owned(), public_key))
+}
+
+/// Verifies the HTTP signature of an incoming request, and returns the ID
+/// of the actor who signed it. The signature must cover the request target,
+/// the date, and the digest of the body, which are checked as well.
+async fn verify_signature(
+    client: &Client,
+    actor_key: &ActorKey,
+    public_key_cache: &PublicKeyCache,
+    method: &Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<String, ErrorResponse> {
+    let error = (
+        StatusCode::UNAUTHORIZED,
+        "missing or invalid HTTP signature",
+    );
+
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    let parameters: Vec<(String, String)> = Regex::new(r#"(\w+)="([^"]*)""#)
+        .unwrap()
+        .captures_iter(header("signature").ok_or(error)?)
+        .map(|captures| (captures[1].to_owned(), captures[2].to_owned()))
+        .collect();
+
+    let parameter = |name: &str| {
+        parameters
+            .iter()
+            .find(|(parameter_name, _)| parameter_name == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    if !matches!(parameter("algorithm"), None | Some("rsa-sha256" | "hs2019")) {
+        return Err(error);
+    }
+
+    let signed_headers: Vec<&str> = parameter("headers")
+        .unwrap_or("date")
+        .split_whitespace()
+        .collect();
+
+    if !["(request-target)", "date", "digest"]
+        .iter()
+        .all(|name| signed_headers.contains(name))
+    {
+        return Err(error);
+    }
+
+    let date = DateTime::parse_from_rfc2822(header("date").ok_or(error)?).map_err(|_| error)?;
+
+    if (Utc::now().timestamp() - date.timestamp()).abs() > MAX_CLOCK_SKEW_SECONDS {
+        return Err(error);
+    }
+
+    let expected_digest = base64::encode(Sha256::digest(body));
+
+    let is_digest_valid = header("digest").ok_or(error)?.split(',').any(|digest| {
+        digest
+            .trim()
+            .split_once('=')
+            .map_or(false, |(algorithm, value)| {
+                algorithm.eq_ignore_ascii_case("SHA-256") && value == expected_digest
+            })
+    });
+
+    if !is_digest_valid {
+        return Err(error);
+    }
+
+    let signing_string = signed_headers
+        .iter()
+        .map(|name| {
+            if *name == "(request-target)" {
+                Ok(format!(
+                    "(request-target): {} {}",
+                    method.as_str().to_lowercase(),
+                    uri.path_and_query()
+                        .map_or(uri.path(), |path_and_query| path_and_query.as_str()),
+                ))
+            } else {
+                Ok(format!("{}: {}", name, header(name).ok_or(error)?))
+            }
+        })
+        .collect::<Result<Vec<_>, ErrorResponse>>()?
+        .join("\n");
+
+    let key_id = parameter("keyId").ok_or(error)?;
+
+    let signature = base64::decode(parameter("signature").ok_or(error)?).map_err(|_| error)?;
+    let signature = Signature::try_from(signature.as_slice()).map_err(|_| error)?;
+
+    let is_signature_valid = |public_key: RsaPublicKey| {
+        VerifyingKey::<Sha256>::new(public_key)
+            .verify(signing_string.as_bytes(), &signature)
+            .is_ok()
+    };
+
+    if let Some((owner, public_key)) = public_key_cache.get(key_id) {
+        if is_signature_valid(public_key) {
+            return Ok(owner);
+        }
+    }
+
+    // The key may have changed since it was cached.
+    let (owner, public_key) = fetch_public_key(client, actor_key, key_id).await?;
+
+    public_key_cache.insert(key_id, &owner, &public_key);
+
+    if is_signature_valid(public_key) {
+        Ok(owner)
+    } else {
+        Err(error)
+    }
+}
+
+/// Receives activities from other servers. Only following and unfollowing
+/// are supported; all other activities are accepted but ignored.
+async fn post_inbox(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref public_key_cache): Extension<PublicKeyCache>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let client = http_client().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to create HTTP client",
+        )
+    })?;
+
+    receive_activity(
+        database_connection,
+        &client,
+        public_key_cache,
+        &method,
+        &uri,
+        &headers,
+        &body,
+    )
+    .await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Handles an activity received in the inbox, using `client` for requests to other servers.
+async fn receive_activity(
+    database_connection: &DatabaseConnection,
+    client: &Client,
+    public_key_cache: &PublicKeyCache,
+    method: &Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(), ErrorResponse> {
+    let settings = settings(database_connection).await?;
+    let base_url = site_base_url(&settings)?;
+    let actor_key = ActorKey::new(&settings, base_url)?;
+    let actor_url = actor_url(base_url);
+
+    let activity: Value = serde_json::from_slice(body)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "invalid JSON request body"))?;
+
+    let signer = verify_signature(
+        client,
+        &actor_key,
+        public_key_cache,
+        method,
+        uri,
+        headers,
+        body,
+    )
+    .await?;
+
+    let actor = object_id(&activity["actor"])
+        .ok_or((StatusCode::BAD_REQUEST, "activity has no actor"))?
+        .to_owned();
+
+    if actor != signer {
+        return Err((StatusCode::FORBIDDEN, "activity not signed by its actor"));
+    }
+
+    match activity["type"].as_str() {
+        Some("Follow") if object_id(&activity["object"]) == Some(&actor_url) => {
+            add_follower(
+                database_connection,
+                client.clone(),
+                actor_key,
+                &actor_url,
+                &actor,
+                activity,
+            )
+            .await?;
+        }
+        Some("Undo") if activity["object"]["type"] == "Follow" => {
+            if let Some(follower) = Follower::find()
+                .filter(follower::Column::Actor.eq(actor))
+                .one(database_connection)
+                .await
+                .map_err(|_| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "unable to retrieve follower",
+                    )
+                })?
+            {
+                follower.delete(database_connection).await.map_err(|_| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "unable to delete follower",
+                    )
+                })?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Stores the actor with the ID `follower_id` as a follower, and accepts `follow`.
+/// Following again only updates the inbox, in case it has changed.
+async fn add_follower(
+    connection: &DatabaseConnection,
+    client: Client,
+    actor_key: ActorKey,
+    actor_url: &str,
+    follower_id: &str,
+    follow: Value,
+) -> Result<(), ErrorResponse> {
+    let follower = fetch_object(&client, &actor_key, follower_id)
+        .await
+        .ok_or((StatusCode::BAD_REQUEST, "unable to retrieve follower"))?;
+
+    let inbox = follower["inbox"]
+        .as_str()
+        .filter(|inbox| is_valid_site_url(inbox))
+        .ok_or((StatusCode::BAD_REQUEST, "follower has no inbox"))?
+        .to_owned();
+
+    let shared_inbox = follower["endpoints"]["sharedInbox"]
+        .as_str()
+        .filter(|shared_inbox| is_valid_site_url(shared_inbox))
+        .unwrap_or(&inbox)
+        .to_owned();
+
+    let existing_follower = Follower::find()
+        .filter(follower::Column::Actor.eq(follower_id))
+        .one(connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve follower",
+            )
+        })?;
+
+    let mut follower: follower::ActiveModel = match existing_follower {
+        Some(follower) => follower.into(),
+        None => follower::ActiveModel {
+            actor: Set(follower_id.to_owned()),
+            followed: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        },
+    };
+
+    follower.inbox = Set(shared_inbox);
+
+    follower
+        .save(connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save follower"))?;
+
+    let accept = json!({
+        "@context": ACTIVITY_STREAMS_CONTEXT,
+        "id": format!("{}#accepts/{}", actor_url, random_token()),
+        "type": "Accept",
+        "actor": actor_url,
+        "object": follow,
+    });
+
+    tokio::spawn(async move {
+        // The follower's server may retry the follow if this fails.
+        let _ = deliver(&client, &actor_key, &inbox, &accept).await;
+    });
+
+    Ok(())
+}
+
+/// Delivers `post` to all followers. This happens in the background, and failures
+/// are ignored, because the post is published regardless.
+pub(crate) async fn deliver_post(
+    connection: &DatabaseConnection,
+    post: &page::Model,
+) -> Result<(), ErrorResponse> {
+    let client = http_client().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to create HTTP client",
+        )
+    })?;
+
+    deliver_to_followers(connection, client, post).await
+}
+
+/// Delivers `post` to the inboxes of all followers using `client`, in the background.
+async fn deliver_to_followers(
+    connection: &DatabaseConnection,
+    client: Client,
+    post: &page::Model,
+) -> Result<(), ErrorResponse> {
+    let settings = settings(connection).await?;
+    let base_url = site_base_url(&settings)?;
+    let actor_key = ActorKey::new(&settings, base_url)?;
+
+    let mut inboxes: Vec<String> = Follower::find()
+        .all(connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve followers",
+            )
+        })?
+        .into_iter()
+        .map(|follower| follower.inbox)
+        .collect();
+
+    // Followers on the same server usually share an inbox.
+    inboxes.sort();
+    inboxes.dedup();
+
+    if inboxes.is_empty() {
+        return Ok(());
+    }
+
+    let activity = create_activity(base_url, post);
+
+    tokio::spawn(async move {
+        for inbox in inboxes {
+            let _ = deliver(&client, &actor_key, &inbox, &activity).await;
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::{http::HeaderValue, Server};
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::Database;
+
+    use super::*;
+
+    /// Smaller than `KEY_BITS`, because generating keys is slow without optimizations.
+    const TEST_KEY_BITS: usize = 1024;
+
+    const SITE_URL: &str = "https://blog.example.com";
+
+    /// Returns a database for a site with two posts per page, and `number_of_posts` posts.
+    async fn database_with_posts(number_of_posts: i64) -> DatabaseConnection {
+        let connection = Database::connect("sqlite::memory:").await.unwrap();
+
+        Migrator::up(&connection, None).await.unwrap();
+
+        let mut settings: settings::ActiveModel = settings(&connection).await.unwrap().into();
+        settings.site_url = Set(SITE_URL.to_owned());
+        settings.posts_per_page = Set(2);
+        settings.actor_private_key =
+            Set(RsaPrivateKey::new(&mut rand::thread_rng(), TEST_KEY_BITS)
+                .unwrap()
+                .to_pkcs8_pem(LineEnding::LF)
+                .unwrap()
+                .to_string());
+        settings.update(&connection).await.unwrap();
+
+        for i in 0..number_of_posts {
+            page::ActiveModel {
+                time: Set(Utc::now().naive_utc() - chrono::Duration::hours(i)),
+                title: Set(format!("Post {}", i)),
+                url: Set(format!("post-{}", i)),
+                content_markdown: Set(String::new()),
+                content_html: Set(format!("<p>Post {}</p>", i)),
+                is_post: Set(true),
+                is_published: Set(true),
+                ..Default::default()
+            }
+            .insert(&connection)
+            .await
+            .unwrap();
+        }
+
+        connection
+    }
+
+    async fn json_body(response: impl IntoResponse) -> Value {
+        serde_json::from_slice(
+            &hyper::body::to_bytes(response.into_response().into_body())
+                .await
+                .unwrap(),
+        )
+        .unwrap()
+    }
+
+    async fn outbox(
+        connection: &DatabaseConnection,
+        page: Option<&str>,
+    ) -> Result<Value, ErrorResponse> {
+        let response = get_outbox(
+            Extension(connection.clone()),
+            Query(OutboxQuery {
+                page: page.map(str::to_owned),
+            }),
+        )
+        .await?;
+
+        Ok(json_body(response).await)
+    }
+
+    #[tokio::test]
+    async fn outbox_is_paged() {
+        let connection = database_with_posts(5).await;
+        let outbox_url = format!("{}{}", SITE_URL, OUTBOX_URL);
+
+        let collection = outbox(&connection, None).await.unwrap();
+        assert_eq!(collection["type"], "OrderedCollection");
+        assert_eq!(collection["totalItems"], 5);
+        assert_eq!(collection["first"], format!("{}?page=1", outbox_url));
+        assert_eq!(collection["last"], format!("{}?page=3", outbox_url));
+        assert!(collection["orderedItems"].is_null());
+
+        let first_page = outbox(&connection, Some("1")).await.unwrap();
+        assert_eq!(first_page["type"], "OrderedCollectionPage");
+        assert_eq!(first_page["partOf"], outbox_url);
+        assert_eq!(first_page["next"], format!("{}?page=2", outbox_url));
+        assert!(first_page["prev"].is_null());
+
+        // Newest posts first.
+        let titles = |page: &Value| {
+            page["orderedItems"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|activity| {
+                    assert_eq!(activity["type"], "Create");
+                    activity["object"]["name"].as_str().unwrap().to_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(titles(&first_page), ["Post 0", "Post 1"]);
+
+        let last_page = outbox(&connection, Some("3")).await.unwrap();
+        assert_eq!(titles(&last_page), ["Post 4"]);
+        assert_eq!(last_page["prev"], format!("{}?page=2", outbox_url));
+        assert!(last_page["next"].is_null());
+
+        for page in ["0", "4", "x"] {
+            assert_eq!(
+                outbox(&connection, Some(page)).await.unwrap_err().0,
+                StatusCode::NOT_FOUND,
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn outbox_without_posts_has_one_page() {
+        let connection = database_with_posts(0).await;
+
+        let collection = outbox(&connection, None).await.unwrap();
+        assert_eq!(collection["totalItems"], 0);
+        assert_eq!(collection["last"], collection["first"]);
+
+        let page = outbox(&connection, Some("1")).await.unwrap();
+        assert_eq!(page["orderedItems"], json!([]));
+    }
+
+    struct MockServer {
+        base_url: String,
+        actor_key: ActorKey,
+        /// How often the actor's public key has been fetched.
+        key_fetches: Arc<AtomicUsize>,
+        /// Requests to the actor's inbox and the server's shared inbox, in the order received.
+        deliveries: Arc<Mutex<Vec<ReceivedRequest>>>,
+    }
+
+    /// Starts a stand-in for another server on a local port, which serves an actor
+    /// whose key is used to sign requests, and an actor whose key claims to be owned
+    /// by an actor on a different server. It is addressed by host name,
+    /// which the test client resolves without filtering.
+    async fn start_server() -> MockServer {
+        let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), TEST_KEY_BITS).unwrap();
+        let public_key_pem = private_key
+            .to_public_key()
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://localhost:{}", listener.local_addr().unwrap().port());
+
+        let key_fetches = Arc::new(AtomicUsize::new(0));
+
+        let actor = json!({
+            "id": format!("{}/actor", base_url),
+            "type": "Person",
+            "inbox": format!("{}/inbox", base_url),
+            "endpoints": {
+                "sharedInbox": format!("{}/shared-inbox", base_url),
+            },
+            "publicKey": {
+                "id": format!("{}/actor#main-key", base_url),
+                "owner": format!("{}/actor", base_url),
+                "publicKeyPem": public_key_pem,
+            },
+        });
+
+        let impostor = json!({
+            "id": format!("{}/impostor", base_url),
+            "type": "Person",
+            "publicKey": {
+                "id": format!("{}/impostor#main-key", base_url),
+                "owner": "https://victim.example/actor",
+                "publicKeyPem": public_key_pem,
+            },
+        });
+
+        let router = Router::new()
+            .route(
+                "/actor",
+                get({
+                    let key_fetches = key_fetches.clone();
+
+                    move || async move {
+                        key_fetches.fetch_add(1, Ordering::SeqCst);
+                        ActivityJson(actor)
+                    }
+                }),
+            )
+            .route(
+                "/impostor",
+                get(move || async move { ActivityJson(impostor) }),
+            );
+
+        let deliveries = Arc::new(Mutex::new(Vec::new()));
+
+        let router = ["/inbox", "/shared-inbox"]
+            .into_iter()
+            .fold(router, |router, path| {
+                let deliveries = deliveries.clone();
+
+                router.route(
+                    path,
+                    post(
+                        move |method: Method, uri: Uri, headers: HeaderMap, body: Bytes| async move {
+                            deliveries.lock().unwrap().push(ReceivedRequest {
+                                method,
+                                uri,
+                                headers,
+                                body: body.to_vec(),
+                            });
+
+                            StatusCode::ACCEPTED
+                        },
+                    ),
+                )
+            });
+
+        tokio::spawn(
+            Server::from_tcp(listener)
+                .unwrap()
+                .serve(router.into_make_service()),
+        );
+
+        MockServer {
+            actor_key: ActorKey {
+                key_id: format!("{}/actor#main-key", base_url),
+                private_key,
+            },
+            base_url,
+            key_fetches,
+            deliveries,
+        }
+    }
+
+    /// The request as the inbox handler receives it.
+    #[derive(Clone)]
+    struct ReceivedRequest {
+        method: Method,
+        uri: Uri,
+        headers: HeaderMap,
+        body: Vec<u8>,
+    }
+
+    fn signed_post(client: &Client, actor_key: &ActorKey, body: &[u8]) -> ReceivedRequest {
+        let request = signed_request(
+            client,
+            actor_key,
+            Method::POST,
+            "https://blog.example.com/activitypub/inbox",
+            Some(body.to_vec()),
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+
+        ReceivedRequest {
+            method: request.method().clone(),
+            uri: request.url().as_str().parse().unwrap(),
+            headers: request.headers().clone(),
+            body: request.body().unwrap().as_bytes().unwrap().to_vec(),
+        }
+    }
+
+    /// Signs the headers of `request` again, after they have been changed.
+    fn sign_again(actor_key: &ActorKey, request: &mut ReceivedRequest) {
+        let header = |name: &str| request.headers[name].to_str().unwrap().to_owned();
+
+        let signing_string = format!(
+            "(request-target): post {}\nhost: {}\ndate: {}\ndigest: {}",
+            request.uri.path(),
+            header("host"),
+            header("date"),
+            header("digest"),
+        );
+
+        let signature = SigningKey::<Sha256>::new(actor_key.private_key.clone())
+            .sign(signing_string.as_bytes())
+            .to_bytes();
+
+        request.headers.insert(
+            "signature",
+            HeaderValue::from_str(&format!(
+                r#"keyId="{}",algorithm="rsa-sha256",headers="(request-target) host date digest",signature="{}""#,
+                actor_key.key_id,
+                base64::encode(signature),
+            ))
+            .unwrap(),
+        );
+    }
+
+    async fn verify(
+        server: &MockServer,
+        public_key_cache: &PublicKeyCache,
+        request: &ReceivedRequest,
+    ) -> Result<String, ErrorResponse> {
+        verify_signature(
+            &Client::new(),
+            &server.actor_key,
+            public_key_cache,
+            &request.method,
+            &request.uri,
+            &request.headers,
+            &request.body,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn verify_signature_accepts_signed_request() {
+        let server = start_server().await;
+        let request = signed_post(&Client::new(), &server.actor_key, br#"{"type":"Follow"}"#);
+
+        assert_eq!(
+            verify(&server, &PublicKeyCache::new(), &request).await,
+            Ok(format!("{}/actor", server.base_url)),
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_signature_rejects_tampered_body() {
+        let server = start_server().await;
+        let mut request = signed_post(&Client::new(), &server.actor_key, br#"{"type":"Follow"}"#);
+
+        request.body = br#"{"type":"Undo"}"#.to_vec();
+
+        assert!(verify(&server, &PublicKeyCache::new(), &request)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn verify_signature_rejects_tampered_digest() {
+        let server = start_server().await;
+        let mut request = signed_post(&Client::new(), &server.actor_key, br#"{"type":"Follow"}"#);
+
+        // The digest matches the new body, but is not the one that was signed.
+        request.body = br#"{"type":"Undo"}"#.to_vec();
+        request.headers.insert(
+            "digest",
+            HeaderValue::from_str(&format!(
+                "SHA-256={}",
+                base64::encode(Sha256::digest(&request.body)),
+            ))
+            .unwrap(),
+        );
+
+        assert!(verify(&server, &PublicKeyCache::new(), &request)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn verify_signature_rejects_stale_date() {
+        let server = start_server().await;
+        let mut request = signed_post(&Client::new(), &server.actor_key, br#"{"type":"Follow"}"#);
+
+        let stale_date = Utc::now() - chrono::Duration::seconds(MAX_CLOCK_SKEW_SECONDS + 60);
+
+        request.headers.insert(
+            "date",
+            HeaderValue::from_str(&stale_date.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+                .unwrap(),
+        );
+
+        // Signed correctly, so only the date is wrong.
+        sign_again(&server.actor_key, &mut request);
+
+        assert!(verify(&server, &PublicKeyCache::new(), &request)
+            .await
+            .is_err());
+        assert_eq!(server.key_fetches.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn verify_signature_rejects_key_owned_by_other_origin() {
+        let server = start_server().await;
+
+        let impostor_key = ActorKey {
+            key_id: format!("{}/impostor#main-key", server.base_url),
+            private_key: server.actor_key.private_key.clone(),
+        };
+
+        let request = signed_post(&Client::new(), &impostor_key, br#"{"type":"Follow"}"#);
+
+        assert!(verify(&server, &PublicKeyCache::new(), &request)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn verify_signature_caches_public_key() {
+        let server = start_server().await;
+        let public_key_cache = PublicKeyCache::new();
+
+        for _ in 0..3 {
+            let request = signed_post(&Client::new(), &server.actor_key, br#"{"type":"Follow"}"#);
+
+            assert!(verify(&server, &public_key_cache, &request).await.is_ok());
+        }
+
+        assert_eq!(server.key_fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn verify_signature_fetches_changed_key() {
+        let server = start_server().await;
+        let public_key_cache = PublicKeyCache::new();
+
+        let old_key = RsaPrivateKey::new(&mut rand::thread_rng(), TEST_KEY_BITS).unwrap();
+
+        public_key_cache.insert(
+            &server.actor_key.key_id,
+            &format!("{}/actor", server.base_url),
+            &old_key.to_public_key(),
+        );
+
+        let request = signed_post(&Client::new(), &server.actor_key, br#"{"type":"Follow"}"#);
+
+        assert!(verify(&server, &public_key_cache, &request).await.is_ok());
+        assert_eq!(server.key_fetches.load(Ordering::SeqCst), 1);
+    }
+
+    /// Waits until the server has received `count` deliveries, and returns them.
+    async fn wait_for_deliveries(server: &MockServer, count: usize) -> Vec<ReceivedRequest> {
+        for _ in 0..100 {
+            let deliveries = server.deliveries.lock().unwrap().clone();
+
+            if deliveries.len() >= count {
+                return deliveries;
+            }
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        panic!("expected {} deliveries", count);
+    }
+
+    async fn site_actor_key(connection: &DatabaseConnection) -> ActorKey {
+        ActorKey::new(&settings(connection).await.unwrap(), SITE_URL).unwrap()
+    }
+
+    /// Verifies that `request` was signed by the site's actor, whose public key
+    /// is cached because the site can't be reached.
+    async fn verify_delivery(
+        server: &MockServer,
+        site_actor_key: &ActorKey,
+        request: &ReceivedRequest,
+    ) -> Result<String, ErrorResponse> {
+        let public_key_cache = PublicKeyCache::new();
+
+        public_key_cache.insert(
+            &site_actor_key.key_id,
+            &actor_url(SITE_URL),
+            &site_actor_key.private_key.to_public_key(),
+        );
+
+        verify(server, &public_key_cache, request).await
+    }
+
+    async fn insert_follower(connection: &DatabaseConnection, actor: &str, inbox: &str) {
+        follower::ActiveModel {
+            actor: Set(actor.to_owned()),
+            inbox: Set(inbox.to_owned()),
+            followed: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        }
+        .insert(connection)
+        .await
+        .unwrap();
+    }
+
+    async fn follower_actors(connection: &DatabaseConnection) -> Vec<String> {
+        Follower::find()
+            .all(connection)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|follower| follower.actor)
+            .collect()
+    }
+
+    /// Receives `activity`, signed by the server's actor.
+    async fn receive(
+        connection: &DatabaseConnection,
+        server: &MockServer,
+        activity: &Value,
+    ) -> Result<(), ErrorResponse> {
+        let request = signed_post(
+            &Client::new(),
+            &server.actor_key,
+            &serde_json::to_vec(activity).unwrap(),
+        );
+
+        receive_activity(
+            connection,
+            &Client::new(),
+            &PublicKeyCache::new(),
+            &request.method,
+            &request.uri,
+            &request.headers,
+            &request.body,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn deliver_posts_signed_activity() {
+        let connection = database_with_posts(1).await;
+        let server = start_server().await;
+        let site_actor_key = site_actor_key(&connection).await;
+
+        let post = published_pages().one(&connection).await.unwrap().unwrap();
+        let activity = create_activity(SITE_URL, &post);
+
+        deliver(
+            &Client::new(),
+            &site_actor_key,
+            &format!("{}/inbox", server.base_url),
+            &activity,
+        )
+        .await
+        .unwrap();
+
+        let deliveries = wait_for_deliveries(&server, 1).await;
+        assert_eq!(deliveries[0].uri.path(), "/inbox");
+        assert_eq!(
+            serde_json::from_slice::<Value>(&deliveries[0].body).unwrap(),
+            activity,
+        );
+        assert_eq!(
+            verify_delivery(&server, &site_actor_key, &deliveries[0]).await,
+            Ok(actor_url(SITE_URL)),
+        );
+    }
+
+    #[tokio::test]
+    async fn deliver_post_delivers_once_per_inbox() {
+        let connection = database_with_posts(1).await;
+        let server = start_server().await;
+        let site_actor_key = site_actor_key(&connection).await;
+
+        let shared_inbox = format!("{}/shared-inbox", server.base_url);
+        insert_follower(&connection, "https://a.example/actor", &shared_inbox).await;
+        insert_follower(&connection, "https://b.example/actor", &shared_inbox).await;
+        insert_follower(
+            &connection,
+            "https://c.example/actor",
+            &format!("{}/inbox", server.base_url),
+        )
+        .await;
+
+        let post = published_pages().one(&connection).await.unwrap().unwrap();
+
+        deliver_to_followers(&connection, Client::new(), &post)
+            .await
+            .unwrap();
+
+        wait_for_deliveries(&server, 2).await;
+
+        // Give a third delivery, which there shouldn't be, time to arrive.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let mut deliveries = server.deliveries.lock().unwrap().clone();
+        assert_eq!(deliveries.len(), 2);
+
+        deliveries.sort_by_key(|delivery| delivery.uri.path().to_owned());
+        assert_eq!(deliveries[0].uri.path(), "/inbox");
+        assert_eq!(deliveries[1].uri.path(), "/shared-inbox");
+
+        for delivery in &deliveries {
+            let activity: Value = serde_json::from_slice(&delivery.body).unwrap();
+            assert_eq!(activity["type"], "Create");
+            assert_eq!(activity["object"]["name"], "Post 0");
+            assert!(verify_delivery(&server, &site_actor_key, delivery)
+                .await
+                .is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn inbox_follow_stores_follower_and_accepts() {
+        let connection = database_with_posts(0).await;
+        let server = start_server().await;
+        let site_actor_key = site_actor_key(&connection).await;
+
+        let follow = json!({
+            "id": format!("{}/follows/1", server.base_url),
+            "type": "Follow",
+            "actor": format!("{}/actor", server.base_url),
+            "object": actor_url(SITE_URL),
+        });
+
+        receive(&connection, &server, &follow).await.unwrap();
+
+        let followers = Follower::find().all(&connection).await.unwrap();
+        assert_eq!(followers.len(), 1);
+        assert_eq!(followers[0].actor, format!("{}/actor", server.base_url));
+        assert_eq!(
+            followers[0].inbox,
+            format!("{}/shared-inbox", server.base_url),
+        );
+
+        // The accept goes to the follower's own inbox.
+        let deliveries = wait_for_deliveries(&server, 1).await;
+        assert_eq!(deliveries[0].uri.path(), "/inbox");
+
+        let accept: Value = serde_json::from_slice(&deliveries[0].body).unwrap();
+        assert_eq!(accept["type"], "Accept");
+        assert_eq!(accept["actor"], actor_url(SITE_URL));
+        assert_eq!(accept["object"], follow);
+        assert!(verify_delivery(&server, &site_actor_key, &deliveries[0])
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn inbox_follow_of_other_actor_is_ignored() {
+        let connection = database_with_posts(0).await;
+        let server = start_server().await;
+
+        let follow = json!({
+            "type": "Follow",
+            "actor": format!("{}/actor", server.base_url),
+            "object": "https://other.example/actor",
+        });
+
+        receive(&connection, &server, &follow).await.unwrap();
+
+        assert!(follower_actors(&connection).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn inbox_undo_removes_follower() {
+        let connection = database_with_posts(0).await;
+        let server = start_server().await;
+
+        let actor = format!("{}/actor", server.base_url);
+        let victim = "https://victim.example/actor";
+
+        insert_follower(&connection, &actor, &format!("{}/inbox", server.base_url)).await;
+        insert_follower(&connection, victim, "https://victim.example/inbox").await;
+
+        let undo = |actor: &str| {
+            json!({
+                "type": "Undo",
+                "actor": actor,
+                "object": {
+                    "type": "Follow",
+                    "actor": actor,
+                    "object": actor_url(SITE_URL),
+                },
+            })
+        };
+
+        // Signed by a different actor than the one who is unfollowing.
+        assert_eq!(
+            receive(&connection, &server, &undo(victim))
+                .await
+                .unwrap_err()
+                .0,
+            StatusCode::FORBIDDEN,
+        );
+        assert_eq!(follower_actors(&connection).await.len(), 2);
+
+        receive(&connection, &server, &undo(&actor)).await.unwrap();
+        assert_eq!(follower_actors(&connection).await, [victim]);
+    }
+}
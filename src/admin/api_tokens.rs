@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use askama::Template;
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    Extension, Form,
+};
+use chrono::Utc;
+use entity::{
+    api_token,
+    prelude::ApiToken,
+    sea_orm_active_enums::{ApiTokenScope, Role},
+    user,
+};
+use sea_orm::{
+    ActiveEnum, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait,
+    QueryFilter, QueryOrder, Set,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{random_token, ErrorResponse, HtmlTemplate, ADMIN_URL_PREFIX};
+
+/// Returns the hex-encoded SHA-256 hash of `token`, which is what is stored in the database.
+/// Unlike passwords, tokens are random and long, so a fast, unsalted hash is sufficient,
+/// and allows looking up tokens by their hash.
+pub(crate) fn hash_api_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+#[derive(Template)]
+#[template(path = "admin/api_tokens.html")]
+struct ApiTokensTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    user: user::Model,
+    tokens: Vec<api_token::Model>,
+    /// Only available right after the token has been created,
+    /// because the token itself is not stored.
+    new_token: Option<String>,
+}
+
+async fn api_tokens_template(
+    connection: &DatabaseConnection,
+    user: &user::Model,
+    new_token: Option<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    Ok(HtmlTemplate(ApiTokensTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
+        title: "API tokens",
+        user: user.clone(),
+        tokens: user
+            .find_related(ApiToken)
+            .order_by_desc(api_token::Column::Created)
+            .all(connection)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "unable to retrieve API tokens",
+                )
+            })?,
+        new_token,
+    }))
+}
+
+pub(super) async fn get_api_tokens(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    api_tokens_template(database_connection, user, None).await
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ApiTokenInput {
+    name: String,
+    scope: String,
+}
+
+pub(super) async fn post_create_api_token(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Form(ref api_token_input): Form<ApiTokenInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let name = api_token_input.name.trim();
+
+    if name.is_empty() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, "name must not be empty"));
+    }
+
+    let scope = ApiTokenScope::try_from_value(&api_token_input.scope)
+        .map_err(|_| (StatusCode::UNPROCESSABLE_ENTITY, "invalid scope"))?;
+
+    // A token cannot grant more than its user is allowed to do anyway,
+    // but offering such a token would be misleading.
+    if scope == ApiTokenScope::SettingsWrite && user.role < Role::Admin {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "only admins can create tokens that change settings",
+        ));
+    }
+
+    let token = random_token();
+
+    api_token::ActiveModel {
+        user_id: Set(user.id),
+        name: Set(name.to_owned()),
+        token_hash: Set(hash_api_token(&token)),
+        scope: Set(scope),
+        created: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(database_connection)
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to create API token",
+        )
+    })?;
+
+    api_tokens_template(database_connection, user, Some(token)).await
+}
+
+pub(super) async fn post_revoke_api_token(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(token_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let token_id: i32 = token_id
+        .parse()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "invalid API token ID"))?;
+
+    let result = ApiToken::delete_many()
+        .filter(api_token::Column::Id.eq(token_id))
+        .filter(api_token::Column::UserId.eq(user.id))
+        .exec(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to revoke API token",
+            )
+        })?;
+
+    if result.rows_affected == 0 {
+        return Err((StatusCode::NOT_FOUND, "API token not found"));
+    }
+
+    Ok(Redirect::to(&format!("{}/api-tokens", ADMIN_URL_PREFIX)))
+}
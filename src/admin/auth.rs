@@ -0,0 +1,382 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use askama::Template;
+use axum::{
+    extract::Query,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+    Extension, Form,
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use chrono::{Duration, Utc};
+use entity::{
+    prelude::{Session, User},
+    sea_orm_active_enums::Role,
+    session, user,
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    Set,
+};
+use serde::Deserialize;
+
+use crate::{random_token, ErrorResponse, HtmlTemplate, ADMIN_URL_PREFIX};
+
+const SESSION_COOKIE_NAME: &str = "session";
+
+const SESSION_LIFETIME_DAYS: i64 = 30;
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+pub(super) fn hash_password(password: &str) -> Result<String, ErrorResponse> {
+    Argon2::default()
+        .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))
+        .map(|password_hash| password_hash.to_string())
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to hash password"))
+}
+
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    match PasswordHash::new(password_hash) {
+        Ok(password_hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &password_hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+pub(super) fn validate_password(password: &str) -> Result<(), ErrorResponse> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "password too short, must contain at least 8 characters",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+async fn has_users(connection: &DatabaseConnection) -> Result<bool, ErrorResponse> {
+    Ok(User::find().count(connection).await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to retrieve users",
+        )
+    })? > 0)
+}
+
+/// Returns the user the session cookie in `jar` belongs to,
+/// or `None` if there is no valid session.
+pub(crate) async fn session_user(
+    connection: &DatabaseConnection,
+    jar: &CookieJar,
+) -> Result<Option<user::Model>, ErrorResponse> {
+    let token = match jar.get(SESSION_COOKIE_NAME) {
+        Some(cookie) => cookie.value().to_owned(),
+        None => return Ok(None),
+    };
+
+    Ok(Session::find()
+        .filter(session::Column::Token.eq(token))
+        .filter(session::Column::Expires.gt(Utc::now().naive_utc()))
+        .find_also_related(User)
+        .one(connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve session",
+            )
+        })?
+        .and_then(|(_, user)| user))
+}
+
+async fn start_session(
+    connection: &DatabaseConnection,
+    jar: CookieJar,
+    user: &user::Model,
+) -> Result<CookieJar, ErrorResponse> {
+    let now = Utc::now().naive_utc();
+
+    // Expired sessions are useless, so this is a good opportunity to clean them up.
+    Session::delete_many()
+        .filter(session::Column::Expires.lte(now))
+        .exec(connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to delete expired sessions",
+            )
+        })?;
+
+    let session = session::ActiveModel {
+        token: Set(random_token()),
+        user_id: Set(user.id),
+        expires: Set(now + Duration::days(SESSION_LIFETIME_DAYS)),
+        ..Default::default()
+    }
+    .insert(connection)
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to create session",
+        )
+    })?;
+
+    // The cookie itself never expires; the session's lifetime is enforced
+    // by the server using the expiration time stored in the database.
+    Ok(jar.add(
+        Cookie::build(SESSION_COOKIE_NAME, session.token)
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .permanent()
+            .finish(),
+    ))
+}
+
+/// Middleware that makes the logged-in user available to handlers,
+/// and redirects to the login page if there is none.
+pub(super) async fn require_login<B>(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    jar: CookieJar,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    match session_user(database_connection, &jar).await {
+        Ok(Some(user)) => {
+            request.extensions_mut().insert(user);
+            next.run(request).await
+        }
+        Ok(None) => Redirect::to(&format!("{}/login", ADMIN_URL_PREFIX)).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+pub(crate) fn require_role(user: &user::Model, role: Role) -> Result<(), ErrorResponse> {
+    if user.role >= role {
+        Ok(())
+    } else {
+        Err((StatusCode::FORBIDDEN, "insufficient permissions"))
+    }
+}
+
+async fn require_role_layer<B>(role: Role, request: Request<B>, next: Next<B>) -> Response {
+    // `require_login` must run first to make the user available.
+    let result = match request.extensions().get::<user::Model>() {
+        Some(user) => require_role(user, role),
+        None => Err((StatusCode::UNAUTHORIZED, "not logged in")),
+    };
+
+    match result {
+        Ok(()) => next.run(request).await,
+        Err(error) => error.into_response(),
+    }
+}
+
+/// Middleware that restricts access to editors and admins.
+pub(super) async fn require_editor<B>(request: Request<B>, next: Next<B>) -> Response {
+    require_role_layer(Role::Editor, request, next).await
+}
+
+/// Middleware that restricts access to admins.
+pub(super) async fn require_admin<B>(request: Request<B>, next: Next<B>) -> Response {
+    require_role_layer(Role::Admin, request, next).await
+}
+
+/// Returns whether `url` is a path on this site, which makes it safe to redirect to.
+/// Browsers remove control characters such as tabs and newlines from URLs,
+/// so `/\t/example.com` would lead to another site.
+fn is_local_path(url: &str) -> bool {
+    url.starts_with('/')
+        && !url.starts_with("//")
+        && !url.contains('\\')
+        && !url.chars().any(char::is_control)
+}
+
+#[derive(Template)]
+#[template(path = "admin/login.html")]
+struct LoginTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    next: String,
+}
+
+/// The page to go to after logging in, if other than the post list.
+#[derive(Deserialize)]
+pub(super) struct LoginQuery {
+    #[serde(default)]
+    next: String,
+}
+
+pub(super) async fn get_login(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Query(login_query): Query<LoginQuery>,
+) -> Result<Response, ErrorResponse> {
+    if !has_users(database_connection).await? {
+        return Ok(Redirect::to(&format!("{}/setup", ADMIN_URL_PREFIX)).into_response());
+    }
+
+    Ok(HtmlTemplate(LoginTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        title: "Log in",
+        next: login_query.next,
+    })
+    .into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct LoginInput {
+    name: String,
+    password: String,
+    #[serde(default)]
+    next: String,
+}
+
+pub(super) async fn post_login(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    jar: CookieJar,
+    Form(ref login_input): Form<LoginInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let user = User::find()
+        .filter(user::Column::Name.eq(login_input.name.as_str()))
+        .one(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve user"))?
+        .filter(|user| verify_password(&login_input.password, &user.password_hash))
+        .ok_or((StatusCode::UNAUTHORIZED, "invalid user name or password"))?;
+
+    Ok((
+        start_session(database_connection, jar, &user).await?,
+        Redirect::to(&if is_local_path(&login_input.next) {
+            login_input.next.clone()
+        } else {
+            format!("{}/posts", ADMIN_URL_PREFIX)
+        }),
+    ))
+}
+
+#[derive(Template)]
+#[template(path = "admin/setup.html")]
+struct SetupTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+}
+
+pub(super) async fn get_setup(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+) -> Result<Response, ErrorResponse> {
+    if has_users(database_connection).await? {
+        return Ok(Redirect::to(&format!("{}/login", ADMIN_URL_PREFIX)).into_response());
+    }
+
+    Ok(HtmlTemplate(SetupTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        title: "Set up",
+    })
+    .into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct SetupInput {
+    name: String,
+    password: String,
+    password_confirmation: String,
+}
+
+pub(super) async fn post_setup(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    jar: CookieJar,
+    Form(ref setup_input): Form<SetupInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    // Setup is only possible as long as no account exists.
+    // After that, creating accounts requires logging in.
+    if has_users(database_connection).await? {
+        return Err((StatusCode::FORBIDDEN, "setup has already been completed"));
+    }
+
+    if setup_input.name.trim().is_empty() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "user name must not be empty",
+        ));
+    }
+
+    validate_password(&setup_input.password)?;
+
+    if setup_input.password != setup_input.password_confirmation {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, "passwords do not match"));
+    }
+
+    let user = user::ActiveModel {
+        name: Set(setup_input.name.trim().to_owned()),
+        password_hash: Set(hash_password(&setup_input.password)?),
+        role: Set(Role::Admin),
+        ..Default::default()
+    }
+    .insert(database_connection)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to create user"))?;
+
+    Ok((
+        start_session(database_connection, jar, &user).await?,
+        Redirect::to(&format!("{}/posts", ADMIN_URL_PREFIX)),
+    ))
+}
+
+pub(super) async fn get_logout(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    if let Some(cookie) = jar.get(SESSION_COOKIE_NAME) {
+        Session::delete_many()
+            .filter(session::Column::Token.eq(cookie.value()))
+            .exec(database_connection)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "unable to delete session",
+                )
+            })?;
+    }
+
+    Ok((
+        jar.remove(Cookie::build(SESSION_COOKIE_NAME, "").path("/").finish()),
+        Redirect::to(&format!("{}/login", ADMIN_URL_PREFIX)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_local_path_rejects_other_sites() {
+        assert!(is_local_path("/-/posts"));
+        assert!(is_local_path(
+            "/indieauth/auth?client_id=https://app.example/"
+        ));
+
+        for url in [
+            "",
+            "https://evil.example",
+            "//evil.example",
+            "/\\evil.example",
+            "\\/evil.example",
+            "/\t/evil.example",
+            "/\n/evil.example",
+            "/\r\n/evil.example",
+            "/\u{0}/evil.example",
+        ] {
+            assert!(!is_local_path(url), "{:?}", url);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use askama::Template;
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    Extension,
+};
+use entity::{
+    comment, page,
+    prelude::{Comment, Page},
+    sea_orm_active_enums::{CommentStatus, Role},
+    user,
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter,
+    QueryOrder, Set,
+};
+use serde::Deserialize;
+
+use crate::{ErrorResponse, HtmlTemplate, ADMIN_URL_PREFIX};
+
+/// The statuses comments can be filtered by, in the order they are listed in the admin interface.
+const STATUSES: [(&str, &str); 4] = [
+    ("pending", "Pending"),
+    ("approved", "Approved"),
+    ("rejected", "Rejected"),
+    ("spam", "Spam"),
+];
+
+fn parse_status(status: &str) -> Result<CommentStatus, ErrorResponse> {
+    match status {
+        "pending" => Ok(CommentStatus::Pending),
+        "approved" => Ok(CommentStatus::Approved),
+        "rejected" => Ok(CommentStatus::Rejected),
+        "spam" => Ok(CommentStatus::Spam),
+        _ => Err((StatusCode::BAD_REQUEST, "invalid comment status")),
+    }
+}
+
+/// Returns the URL of the list of comments with the same status as `comment`.
+fn comment_list_url(comment: &comment::Model) -> String {
+    format!(
+        "{}/comments?status={}",
+        ADMIN_URL_PREFIX,
+        match comment.status {
+            CommentStatus::Pending => "pending",
+            CommentStatus::Approved => "approved",
+            CommentStatus::Rejected => "rejected",
+            CommentStatus::Spam => "spam",
+        },
+    )
+}
+
+async fn comment_by_id(
+    connection: &DatabaseConnection,
+    id: String,
+) -> Result<comment::Model, ErrorResponse> {
+    Comment::find_by_id(
+        id.parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid comment ID"))?,
+    )
+    .one(connection)
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to retrieve comment",
+        )
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "comment not found"))
+}
+
+#[derive(Template)]
+#[template(path = "admin/comments.html")]
+struct CommentsTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    /// Value, label and whether the status is the one currently shown.
+    statuses: Vec<(&'a str, &'a str, bool)>,
+    status: String,
+    comments: Vec<(comment::Model, Option<page::Model>)>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct CommentsQuery {
+    #[serde(default = "default_status")]
+    status: String,
+}
+
+fn default_status() -> String {
+    "pending".to_owned()
+}
+
+pub(super) async fn get_comments(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Query(comments_query): Query<CommentsQuery>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let status = parse_status(&comments_query.status)?;
+
+    Ok(HtmlTemplate(CommentsTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
+        title: "Comments",
+        statuses: STATUSES
+            .iter()
+            .map(|(value, label)| (*value, *label, *value == comments_query.status))
+            .collect(),
+        status: comments_query.status,
+        comments: Comment::find()
+            .filter(comment::Column::Status.eq(status))
+            .order_by_desc(comment::Column::Time)
+            .find_also_related(Page)
+            .all(database_connection)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "unable to retrieve comments",
+                )
+            })?,
+    }))
+}
+
+async fn set_comment_status(
+    database_connection: &DatabaseConnection,
+    comment_id: String,
+    status: CommentStatus,
+) -> Result<Redirect, ErrorResponse> {
+    let comment = comment_by_id(database_connection, comment_id).await?;
+
+    // Return to the list the comment was moderated from.
+    let redirect = Redirect::to(&comment_list_url(&comment));
+
+    let mut comment: comment::ActiveModel = comment.into();
+
+    comment.status = Set(status);
+
+    comment
+        .update(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save comment"))?;
+
+    Ok(redirect)
+}
+
+pub(super) async fn post_approve_comment(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(comment_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    set_comment_status(database_connection, comment_id, CommentStatus::Approved).await
+}
+
+pub(super) async fn post_reject_comment(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(comment_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    set_comment_status(database_connection, comment_id, CommentStatus::Rejected).await
+}
+
+pub(super) async fn post_spam_comment(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(comment_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    set_comment_status(database_connection, comment_id, CommentStatus::Spam).await
+}
+
+pub(super) async fn post_delete_comment(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(comment_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let comment = comment_by_id(database_connection, comment_id).await?;
+
+    let redirect = comment_list_url(&comment);
+
+    comment.delete(database_connection).await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to delete comment",
+        )
+    })?;
+
+    Ok(Redirect::to(&redirect))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::header::LOCATION;
+    use chrono::Utc;
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::Database;
+
+    use super::*;
+
+    async fn database_with_comment(status: CommentStatus) -> (DatabaseConnection, comment::Model) {
+        let connection = Database::connect("sqlite::memory:").await.unwrap();
+
+        Migrator::up(&connection, None).await.unwrap();
+
+        let post = page::ActiveModel {
+            time: Set(Utc::now().naive_utc()),
+            title: Set("A post".to_owned()),
+            url: Set("a-post".to_owned()),
+            content_markdown: Set(String::new()),
+            content_html: Set(String::new()),
+            is_post: Set(true),
+            is_published: Set(true),
+            ..Default::default()
+        }
+        .insert(&connection)
+        .await
+        .unwrap();
+
+        let comment = comment::ActiveModel {
+            page_id: Set(post.id),
+            time: Set(Utc::now().naive_utc()),
+            author_name: Set("Jane".to_owned()),
+            author_url: Set(String::new()),
+            content_markdown: Set("Hi".to_owned()),
+            content_html: Set("<p>Hi</p>\n".to_owned()),
+            status: Set(status),
+            ..Default::default()
+        }
+        .insert(&connection)
+        .await
+        .unwrap();
+
+        (connection, comment)
+    }
+
+    fn location(response: impl IntoResponse) -> String {
+        response.into_response().headers()[LOCATION]
+            .to_str()
+            .unwrap()
+            .to_owned()
+    }
+
+    async fn status(connection: &DatabaseConnection, comment: &comment::Model) -> CommentStatus {
+        Comment::find_by_id(comment.id)
+            .one(connection)
+            .await
+            .unwrap()
+            .unwrap()
+            .status
+    }
+
+    #[tokio::test]
+    async fn moderation_changes_status_and_returns_to_previous_list() {
+        let (connection, comment) = database_with_comment(CommentStatus::Pending).await;
+        let id = || Path(comment.id.to_string());
+
+        let response = post_approve_comment(Extension(connection.clone()), id())
+            .await
+            .unwrap();
+        assert_eq!(location(response), "/-/comments?status=pending");
+        assert_eq!(status(&connection, &comment).await, CommentStatus::Approved);
+
+        let response = post_spam_comment(Extension(connection.clone()), id())
+            .await
+            .unwrap();
+        assert_eq!(location(response), "/-/comments?status=approved");
+        assert_eq!(status(&connection, &comment).await, CommentStatus::Spam);
+
+        let response = post_reject_comment(Extension(connection.clone()), id())
+            .await
+            .unwrap();
+        assert_eq!(location(response), "/-/comments?status=spam");
+        assert_eq!(status(&connection, &comment).await, CommentStatus::Rejected);
+
+        // A rejected comment can still be approved.
+        post_approve_comment(Extension(connection.clone()), id())
+            .await
+            .unwrap();
+        assert_eq!(status(&connection, &comment).await, CommentStatus::Approved);
+    }
+
+    #[tokio::test]
+    async fn delete_removes_comment() {
+        let (connection, comment) = database_with_comment(CommentStatus::Spam).await;
+
+        let response =
+            post_delete_comment(Extension(connection.clone()), Path(comment.id.to_string()))
+                .await
+                .unwrap();
+        assert_eq!(location(response), "/-/comments?status=spam");
+        assert!(Comment::find().all(&connection).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn moderation_of_unknown_comment_fails() {
+        let (connection, comment) = database_with_comment(CommentStatus::Pending).await;
+
+        let result = post_approve_comment(
+            Extension(connection.clone()),
+            Path((comment.id + 1).to_string()),
+        )
+        .await;
+        assert_eq!(result.err().unwrap().0, StatusCode::NOT_FOUND);
+
+        let result =
+            post_approve_comment(Extension(connection.clone()), Path("x".to_owned())).await;
+        assert_eq!(result.err().unwrap().0, StatusCode::BAD_REQUEST);
+
+        assert_eq!(status(&connection, &comment).await, CommentStatus::Pending);
+    }
+
+    #[test]
+    fn parse_status_rejects_unknown_status() {
+        assert_eq!(parse_status("approved"), Ok(CommentStatus::Approved));
+        assert_eq!(
+            parse_status("deleted").unwrap_err().0,
+            StatusCode::BAD_REQUEST,
+        );
+    }
+}
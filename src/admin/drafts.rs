@@ -0,0 +1,131 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    Extension, Json,
+};
+use chrono::Utc;
+use entity::{post_draft, prelude::PostDraft, user};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, Set,
+};
+
+use crate::{
+    admin::posts::{editable_post_by_id, parse_post_id, PostInput},
+    ErrorResponse, ADMIN_URL_PREFIX,
+};
+
+/// Matches the draft of `user` for the post with ID `post_id`,
+/// or for a post that has not been saved yet if `post_id` is `None`.
+fn draft_condition(user: &user::Model, post_id: Option<i32>) -> Condition {
+    Condition::all()
+        .add(post_draft::Column::UserId.eq(user.id))
+        .add(match post_id {
+            Some(post_id) => post_draft::Column::PageId.eq(post_id),
+            None => post_draft::Column::PageId.is_null(),
+        })
+}
+
+pub(super) async fn draft(
+    connection: &DatabaseConnection,
+    user: &user::Model,
+    post_id: Option<i32>,
+) -> Result<Option<post_draft::Model>, ErrorResponse> {
+    PostDraft::find()
+        .filter(draft_condition(user, post_id))
+        .one(connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve draft",
+            )
+        })
+}
+
+pub(super) async fn delete_draft(
+    connection: &DatabaseConnection,
+    user: &user::Model,
+    post_id: Option<i32>,
+) -> Result<(), ErrorResponse> {
+    PostDraft::delete_many()
+        .filter(draft_condition(user, post_id))
+        .exec(connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to delete draft"))?;
+
+    Ok(())
+}
+
+/// Like `parse_post_id`, but also checks that `user` may edit the post.
+async fn editable_post_id(
+    connection: &DatabaseConnection,
+    user: &user::Model,
+    post_id: &str,
+) -> Result<Option<i32>, ErrorResponse> {
+    let post_id = parse_post_id(post_id)?;
+
+    if let Some(post_id) = post_id {
+        editable_post_by_id(connection, user, post_id).await?;
+    }
+
+    Ok(post_id)
+}
+
+/// Stores the current state of the post editor, without changing the post itself.
+/// Called periodically by the editor, so that work is not lost if the browser crashes.
+pub(super) async fn post_save_draft(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(post_id): Path<String>,
+    Json(post_input): Json<PostInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let post_id = editable_post_id(database_connection, user, &post_id).await?;
+
+    let existing_draft = draft(database_connection, user, post_id).await?;
+
+    let mut draft: post_draft::ActiveModel = match existing_draft {
+        Some(draft) => draft.into(),
+        None => post_draft::ActiveModel {
+            page_id: Set(post_id),
+            user_id: Set(user.id),
+            ..Default::default()
+        },
+    };
+
+    draft.save_time = Set(Utc::now().naive_utc());
+    draft.title = Set(post_input.title);
+    draft.url = Set(post_input.url);
+    draft.date = Set(post_input.date);
+    draft.time = Set(post_input.time);
+    draft.tags = Set(post_input.tags);
+    draft.content = Set(post_input.content);
+
+    draft
+        .save(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save draft"))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(super) async fn post_discard_draft(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(post_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    delete_draft(
+        database_connection,
+        user,
+        editable_post_id(database_connection, user, &post_id).await?,
+    )
+    .await?;
+
+    Ok(Redirect::to(&format!(
+        "{}/posts/{}",
+        ADMIN_URL_PREFIX, post_id,
+    )))
+}
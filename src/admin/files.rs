@@ -0,0 +1,319 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use askama::Template;
+use axum::{
+    extract::{Multipart, Path},
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    Extension, Form,
+};
+use chrono::{NaiveDateTime, Utc};
+use entity::{file, prelude::File, sea_orm_active_enums::Role, user};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, FromQueryResult,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use serde::Deserialize;
+
+use crate::{
+    admin::{file_name_to_valid, is_valid_file_name},
+    ErrorResponse, HtmlTemplate, ADMIN_URL_PREFIX,
+};
+
+/// File metadata without the (potentially large) content.
+#[derive(FromQueryResult)]
+struct FileInfo {
+    id: i32,
+    time: NaiveDateTime,
+    name: String,
+    mime_type: String,
+    size: i64,
+}
+
+impl FileInfo {
+    fn url(&self) -> String {
+        format!("/files/{}", self.name)
+    }
+
+    fn markdown_link(&self) -> String {
+        if self.mime_type.starts_with("image/") {
+            format!("![{}]({})", self.name, self.url())
+        } else {
+            format!("[{}]({})", self.name, self.url())
+        }
+    }
+
+    fn formatted_size(&self) -> String {
+        let mut size = self.size as f64;
+
+        for unit in ["bytes", "KiB", "MiB"] {
+            if size < 1024.0 {
+                return if unit == "bytes" {
+                    format!("{} {}", self.size, unit)
+                } else {
+                    format!("{:.1} {}", size, unit)
+                };
+            }
+
+            size /= 1024.0;
+        }
+
+        format!("{:.1} GiB", size)
+    }
+}
+
+async fn file_info_by_id(
+    connection: &DatabaseConnection,
+    id: i32,
+) -> Result<FileInfo, ErrorResponse> {
+    File::find_by_id(id)
+        .select_only()
+        .columns([
+            file::Column::Id,
+            file::Column::Time,
+            file::Column::Name,
+            file::Column::MimeType,
+            file::Column::Size,
+        ])
+        .into_model::<FileInfo>()
+        .one(connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve file"))?
+        .ok_or((StatusCode::NOT_FOUND, "file not found"))
+}
+
+async fn ensure_file_name_available(
+    connection: &DatabaseConnection,
+    name: &str,
+) -> Result<(), ErrorResponse> {
+    let count = File::find()
+        .filter(file::Column::Name.eq(name))
+        .count(connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve files",
+            )
+        })?;
+
+    if count > 0 {
+        Err((StatusCode::CONFLICT, "a file with this name already exists"))
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin/files.html")]
+struct FilesTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    files: Vec<FileInfo>,
+}
+
+pub(super) async fn get_files(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    Ok(HtmlTemplate(FilesTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
+        title: "Files",
+        files: File::find()
+            .select_only()
+            .columns([
+                file::Column::Id,
+                file::Column::Time,
+                file::Column::Name,
+                file::Column::MimeType,
+                file::Column::Size,
+            ])
+            .order_by_desc(file::Column::Time)
+            .into_model::<FileInfo>()
+            .all(database_connection)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "unable to retrieve files",
+                )
+            })?,
+    }))
+}
+
+pub(super) async fn post_upload_files(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| (StatusCode::BAD_REQUEST, "invalid upload"))?
+    {
+        if field.name() != Some("files") {
+            continue;
+        }
+
+        let name = file_name_to_valid(field.file_name().unwrap_or_default());
+
+        // Browsers submit an empty file field if no file was selected.
+        if name.is_empty() {
+            continue;
+        }
+
+        ensure_file_name_available(database_connection, &name).await?;
+
+        let mime_type = match field.content_type() {
+            Some(mime_type) if mime_type != "application/octet-stream" => mime_type.to_owned(),
+            _ => mime_guess::from_path(&name)
+                .first_or_octet_stream()
+                .to_string(),
+        };
+
+        let content = field
+            .bytes()
+            .await
+            .map_err(|_| (StatusCode::BAD_REQUEST, "unable to read uploaded file"))?;
+
+        file::ActiveModel {
+            time: Set(Utc::now().naive_utc()),
+            name: Set(name),
+            mime_type: Set(mime_type),
+            size: Set(content.len() as i64),
+            content: Set(content.to_vec()),
+            ..Default::default()
+        }
+        .insert(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save file"))?;
+    }
+
+    Ok(Redirect::to(&format!("{}/files", ADMIN_URL_PREFIX)))
+}
+
+#[derive(Template)]
+#[template(path = "admin/file.html")]
+struct FileTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    file: FileInfo,
+}
+
+pub(super) async fn get_file(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(file_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let file = file_info_by_id(
+        database_connection,
+        file_id
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid file ID"))?,
+    )
+    .await?;
+
+    Ok(HtmlTemplate(FileTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
+        title: "Edit file",
+        file,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct FileInput {
+    name: String,
+}
+
+pub(super) async fn post_rename_file(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(file_id): Path<String>,
+    Form(ref file_input): Form<FileInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let file = file_info_by_id(
+        database_connection,
+        file_id
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid file ID"))?,
+    )
+    .await?;
+
+    if file_input.name != file.name {
+        if !is_valid_file_name(&file_input.name) {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid file name, must contain only letters (a-z, A-Z), digits (0-9), hyphens (-), underscores (_), and dots (.), and must not start with a dot",
+            ));
+        }
+
+        ensure_file_name_available(database_connection, &file_input.name).await?;
+
+        file::ActiveModel {
+            id: Set(file.id),
+            name: Set(file_input.name.clone()),
+            ..Default::default()
+        }
+        .update(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to rename file"))?;
+    }
+
+    Ok(Redirect::to(&format!(
+        "{}/files/{}",
+        ADMIN_URL_PREFIX, file.id,
+    )))
+}
+
+#[derive(Template)]
+#[template(path = "admin/delete_file.html")]
+struct DeleteFileTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    file: FileInfo,
+}
+
+pub(super) async fn get_delete_file(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(file_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let file = file_info_by_id(
+        database_connection,
+        file_id
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid file ID"))?,
+    )
+    .await?;
+
+    Ok(HtmlTemplate(DeleteFileTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
+        title: "Delete file",
+        file,
+    }))
+}
+
+pub(super) async fn post_delete_file(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(file_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let file = file_info_by_id(
+        database_connection,
+        file_id
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid file ID"))?,
+    )
+    .await?;
+
+    File::delete_by_id(file.id)
+        .exec(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to delete file"))?;
+
+    Ok(Redirect::to(&format!("{}/files", ADMIN_URL_PREFIX)))
+}
@@ -1,32 +1,68 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 // Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
-mod markdown;
-mod posts;
-mod settings;
+pub(crate) mod api_tokens;
+pub(crate) mod auth;
+mod comments;
+mod drafts;
+mod files;
+pub(crate) mod pages;
+pub(crate) mod posts;
+mod preview;
+mod preview_links;
+mod revisions;
+pub(crate) mod settings;
+mod users;
+mod webmentions;
 
 use axum::{
+    extract::DefaultBodyLimit,
+    http::StatusCode,
+    middleware,
     routing::{get, post},
     Router,
 };
 use regex::Regex;
 
+use crate::ErrorResponse;
+
+const MAX_UPLOAD_SIZE: usize = 64 * 1024 * 1024;
+
 pub(super) fn router() -> Router {
-    Router::new()
-        .route("/posts", get(posts::get_posts))
+    let editor_routes = Router::new()
+        .route("/pages", get(pages::get_pages))
         .route(
-            "/posts/:post_id",
-            get(posts::get_post).post(posts::post_save_post),
+            "/pages/:page_id",
+            get(pages::get_page).post(pages::post_save_page),
         )
-        .route("/posts/:post_id/publish", post(posts::post_publish_post))
+        .route("/pages/:page_id/publish", post(pages::post_publish_page))
         .route(
-            "/posts/:post_id/unpublish",
-            post(posts::post_unpublish_post),
+            "/pages/:page_id/unpublish",
+            post(pages::post_unpublish_page),
         )
         .route(
-            "/posts/:post_id/delete",
-            get(posts::get_delete_post).post(posts::post_delete_post),
+            "/pages/:page_id/delete",
+            get(pages::get_delete_page).post(pages::post_delete_page),
         )
+        // Uploaded files are served from the site's origin,
+        // so only trusted users may manage them.
+        .route(
+            "/files",
+            get(files::get_files)
+                .post(files::post_upload_files)
+                .layer(DefaultBodyLimit::max(MAX_UPLOAD_SIZE)),
+        )
+        .route(
+            "/files/:file_id",
+            get(files::get_file).post(files::post_rename_file),
+        )
+        .route(
+            "/files/:file_id/delete",
+            get(files::get_delete_file).post(files::post_delete_file),
+        )
+        .route_layer(middleware::from_fn(auth::require_editor));
+
+    let admin_routes = Router::new()
         .route(
             "/header",
             get(settings::get_header).post(settings::post_header),
@@ -44,13 +80,142 @@ pub(super) fn router() -> Router {
             "/settings",
             get(settings::get_settings).post(settings::post_settings),
         )
+        .route("/comments", get(comments::get_comments))
+        .route(
+            "/comments/:comment_id/approve",
+            post(comments::post_approve_comment),
+        )
+        .route(
+            "/comments/:comment_id/reject",
+            post(comments::post_reject_comment),
+        )
+        .route(
+            "/comments/:comment_id/spam",
+            post(comments::post_spam_comment),
+        )
+        .route(
+            "/comments/:comment_id/delete",
+            post(comments::post_delete_comment),
+        )
+        .route("/users", get(users::get_users))
+        .route(
+            "/users/:user_id",
+            get(users::get_user).post(users::post_save_user),
+        )
+        .route(
+            "/users/:user_id/delete",
+            get(users::get_delete_user).post(users::post_delete_user),
+        )
+        .route("/webmentions", get(webmentions::get_webmentions))
+        .route(
+            "/webmentions/:webmention_id/approve",
+            post(webmentions::post_approve_webmention),
+        )
+        .route(
+            "/webmentions/:webmention_id/delete",
+            post(webmentions::post_delete_webmention),
+        )
+        .route_layer(middleware::from_fn(auth::require_admin));
+
+    // Permissions for individual posts are checked by the post handlers.
+    Router::new()
+        .route("/posts", get(posts::get_posts))
+        .route(
+            "/posts/:post_id",
+            get(posts::get_post).post(posts::post_save_post),
+        )
+        .route("/posts/:post_id/publish", post(posts::post_publish_post))
+        .route(
+            "/posts/:post_id/unpublish",
+            post(posts::post_unpublish_post),
+        )
+        .route(
+            "/posts/:post_id/delete",
+            get(posts::get_delete_post).post(posts::post_delete_post),
+        )
+        .route("/posts/:post_id/draft", post(drafts::post_save_draft))
+        .route(
+            "/posts/:post_id/draft/discard",
+            post(drafts::post_discard_draft),
+        )
+        .route(
+            "/posts/:post_id/preview-links",
+            get(preview_links::get_preview_links).post(preview_links::post_create_preview_link),
+        )
+        .route(
+            "/posts/:post_id/preview-links/:link_id/revoke",
+            post(preview_links::post_revoke_preview_link),
+        )
+        .route("/posts/:post_id/history", get(revisions::get_revisions))
+        .route(
+            "/posts/:post_id/history/:revision_id/restore",
+            post(revisions::post_restore_revision),
+        )
+        .route("/preview", post(preview::post_preview))
+        .route(
+            "/api-tokens",
+            get(api_tokens::get_api_tokens).post(api_tokens::post_create_api_token),
+        )
+        .route(
+            "/api-tokens/:token_id/revoke",
+            post(api_tokens::post_revoke_api_token),
+        )
+        .merge(editor_routes)
+        .merge(admin_routes)
+        // All routes above require the user to be logged in.
+        .route_layer(middleware::from_fn(auth::require_login))
+        .route("/login", get(auth::get_login).post(auth::post_login))
+        .route("/setup", get(auth::get_setup).post(auth::post_setup))
+        .route("/logout", get(auth::get_logout))
 }
 
+/// The first path components of the site's own routes. Pages with these URLs
+/// would be unreachable, or would hide the site's own pages.
+const RESERVED_URLS: [&str; 13] = [
+    "-",
+    "api",
+    "page",
+    "tag",
+    "archive",
+    "search",
+    "preview",
+    "files",
+    "comments",
+    "micropub",
+    "indieauth",
+    "webmention",
+    "activitypub",
+];
+
 fn is_valid_url(url: &str) -> bool {
     Regex::new(r"^[a-zA-Z0-9-]+$").unwrap().is_match(url)
 }
 
-fn title_to_url(title: &str) -> String {
+/// Returns the URL of a page or post, which is `url` if it is valid,
+/// or derived from `title` if `url` is empty.
+fn page_url(url: &str, title: &str) -> Result<String, ErrorResponse> {
+    let url = if url.is_empty() {
+        title_to_url(title)
+    } else if is_valid_url(url) {
+        url.to_owned()
+    } else {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid URL, must contain only letters (a-z, A-Z), digits (0-9), and hyphens (-)",
+        ));
+    };
+
+    if RESERVED_URLS.contains(&url.as_str()) {
+        Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "URL is used by the site itself, please choose another URL",
+        ))
+    } else {
+        Ok(url)
+    }
+}
+
+pub(crate) fn title_to_url(title: &str) -> String {
     let whitespace = Regex::new(r"\s+").unwrap();
     let disallowed_characters = Regex::new(r"[^a-zA-Z0-9-]+").unwrap();
     let hyphens = Regex::new(r"-+").unwrap();
@@ -61,3 +226,48 @@ fn title_to_url(title: &str) -> String {
 
     title.to_lowercase()
 }
+
+pub(crate) fn is_valid_site_url(url: &str) -> bool {
+    Regex::new(r"^https?://[^/\s]+(/\S*)?$")
+        .unwrap()
+        .is_match(url)
+}
+
+fn is_valid_file_name(file_name: &str) -> bool {
+    Regex::new(r"^[a-zA-Z0-9_-][a-zA-Z0-9._-]*$")
+        .unwrap()
+        .is_match(file_name)
+}
+
+fn file_name_to_valid(file_name: &str) -> String {
+    let whitespace = Regex::new(r"\s+").unwrap();
+    let disallowed_characters = Regex::new(r"[^a-zA-Z0-9._-]+").unwrap();
+    let leading_dots = Regex::new(r"^\.+").unwrap();
+
+    let file_name = whitespace.replace_all(file_name, "-");
+    let file_name = disallowed_characters.replace_all(&file_name, "");
+    let file_name = leading_dots.replace(&file_name, "");
+
+    file_name.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_url_rejects_reserved_urls() {
+        assert_eq!(page_url("", "Hello, World!"), Ok("hello-world".to_owned()));
+        assert_eq!(page_url("2023", "Title"), Ok("2023".to_owned()));
+
+        assert!(page_url("not valid", "Title").is_err());
+
+        for url in RESERVED_URLS {
+            assert!(page_url(url, "Title").is_err(), "{}", url);
+        }
+
+        // URLs derived from titles are checked as well.
+        assert!(page_url("", "Archive").is_err());
+        assert!(page_url("", "Search").is_err());
+    }
+}
@@ -0,0 +1,318 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use askama::Template;
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    Extension, Form,
+};
+use chrono::{NaiveDateTime, Utc};
+use entity::{page, prelude::Page, sea_orm_active_enums::Role, user};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter,
+    QueryOrder, Set,
+};
+use serde::Deserialize;
+
+use crate::{
+    admin::page_url, markdown::markdown_to_html, ErrorResponse, HtmlTemplate, ADMIN_URL_PREFIX,
+};
+
+pub(crate) async fn page_by_id(
+    connection: &DatabaseConnection,
+    id: i32,
+) -> Result<page::Model, ErrorResponse> {
+    Page::find_by_id(id)
+        .filter(page::Column::IsPost.eq(false))
+        .one(connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve page"))?
+        .ok_or((StatusCode::NOT_FOUND, "page not found"))
+}
+
+/// Returns all pages, ordered by title.
+pub(crate) async fn all_pages(
+    connection: &DatabaseConnection,
+) -> Result<Vec<page::Model>, ErrorResponse> {
+    Page::find()
+        .filter(page::Column::IsPost.eq(false))
+        .order_by_asc(page::Column::Title)
+        .all(connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve pages",
+            )
+        })
+}
+
+#[derive(Template)]
+#[template(path = "admin/pages.html")]
+struct PagesTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    pages: Vec<page::Model>,
+}
+
+pub(super) async fn get_pages(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    Ok(HtmlTemplate(PagesTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
+        title: "Pages",
+        pages: all_pages(database_connection).await?,
+    }))
+}
+
+#[derive(Template)]
+#[template(path = "admin/page.html")]
+struct PageTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    page: page::Model,
+    is_new: bool,
+}
+
+pub(super) async fn get_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(page_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let is_new = page_id == "new";
+
+    let page = if is_new {
+        page::Model {
+            id: 0,
+            time: NaiveDateTime::MIN,
+            title: String::new(),
+            url: String::new(),
+            content_markdown: String::new(),
+            content_html: String::new(),
+            is_post: false,
+            is_published: false,
+            menu_order: None,
+            author_id: None,
+            announced: None,
+            modified: None,
+        }
+    } else {
+        page_by_id(
+            database_connection,
+            page_id
+                .parse()
+                .map_err(|_| (StatusCode::BAD_REQUEST, "invalid page ID"))?,
+        )
+        .await?
+    };
+
+    Ok(HtmlTemplate(PageTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
+        title: if is_new { "New page" } else { "Edit page" },
+        page,
+        is_new,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PageInput {
+    pub(crate) title: String,
+    pub(crate) url: String,
+    pub(crate) menu_order: String,
+    pub(crate) content: String,
+}
+
+/// Parses a page ID from a URL path, where `new` denotes a page that has not been saved yet.
+fn parse_page_id(page_id: &str) -> Result<Option<i32>, ErrorResponse> {
+    if page_id == "new" {
+        Ok(None)
+    } else {
+        page_id
+            .parse()
+            .map(Some)
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid page ID"))
+    }
+}
+
+/// Creates a new page if `page_id` is `None`, and updates the existing page otherwise.
+pub(crate) async fn save_page(
+    database_connection: &DatabaseConnection,
+    user: &user::Model,
+    page_id: Option<i32>,
+    page_input: &PageInput,
+    set_is_published: Option<bool>,
+) -> Result<page::Model, ErrorResponse> {
+    let mut page = match page_id {
+        None => page::ActiveModel {
+            is_post: Set(false),
+            is_published: Set(false),
+            author_id: Set(Some(user.id)),
+            ..Default::default()
+        },
+        Some(page_id) => page_by_id(database_connection, page_id).await?.into(),
+    };
+
+    page.title = Set(page_input.title.clone());
+
+    page.url = Set(page_url(&page_input.url, &page_input.title)?);
+
+    page.menu_order = Set(if page_input.menu_order.is_empty() {
+        None
+    } else {
+        Some(page_input.menu_order.parse().map_err(|_| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid menu order, must be an integer",
+            )
+        })?)
+    });
+
+    // Pages have no user-facing date, so the time simply records
+    // when the page was last saved.
+    page.time = Set(Utc::now().naive_utc());
+
+    page.content_markdown = Set(page_input.content.clone());
+
+    page.content_html = Set(markdown_to_html(&page_input.content));
+
+    if let Some(is_published) = set_is_published {
+        page.is_published = Set(is_published);
+    }
+
+    if page_id.is_none() {
+        page.insert(database_connection)
+    } else {
+        page.update(database_connection)
+    }
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save page"))
+}
+
+async fn save_page_and_redirect(
+    database_connection: &DatabaseConnection,
+    user: &user::Model,
+    page_id: String,
+    page_input: &PageInput,
+    set_is_published: Option<bool>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let page = save_page(
+        database_connection,
+        user,
+        parse_page_id(&page_id)?,
+        page_input,
+        set_is_published,
+    )
+    .await?;
+
+    Ok(Redirect::to(&format!(
+        "{}/pages/{}",
+        ADMIN_URL_PREFIX, page.id,
+    )))
+}
+
+/// Publishes or unpublishes a page without otherwise changing it.
+pub(crate) async fn set_page_is_published(
+    database_connection: &DatabaseConnection,
+    page_id: i32,
+    is_published: bool,
+) -> Result<page::Model, ErrorResponse> {
+    let mut page: page::ActiveModel = page_by_id(database_connection, page_id).await?.into();
+
+    page.is_published = Set(is_published);
+
+    page.update(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save page"))
+}
+
+pub(super) async fn post_save_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(page_id): Path<String>,
+    Form(ref page_input): Form<PageInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    save_page_and_redirect(database_connection, user, page_id, page_input, None).await
+}
+
+pub(super) async fn post_publish_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(page_id): Path<String>,
+    Form(ref page_input): Form<PageInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    save_page_and_redirect(database_connection, user, page_id, page_input, Some(true)).await
+}
+
+pub(super) async fn post_unpublish_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(page_id): Path<String>,
+    Form(ref page_input): Form<PageInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    save_page_and_redirect(database_connection, user, page_id, page_input, Some(false)).await
+}
+
+#[derive(Template)]
+#[template(path = "admin/delete_page.html")]
+struct DeletePageTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    page: page::Model,
+}
+
+pub(super) async fn get_delete_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(page_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let page = page_by_id(
+        database_connection,
+        page_id
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid page ID"))?,
+    )
+    .await?;
+
+    Ok(HtmlTemplate(DeletePageTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
+        title: "Delete page",
+        page,
+    }))
+}
+
+pub(crate) async fn delete_page(
+    database_connection: &DatabaseConnection,
+    page_id: i32,
+) -> Result<(), ErrorResponse> {
+    page_by_id(database_connection, page_id)
+        .await?
+        .delete(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to delete page"))?;
+
+    Ok(())
+}
+
+pub(super) async fn post_delete_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(page_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    delete_page(
+        database_connection,
+        page_id
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid page ID"))?,
+    )
+    .await?;
+
+    Ok(Redirect::to(&format!("{}/pages", ADMIN_URL_PREFIX)))
+}
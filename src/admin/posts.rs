@@ -3,21 +3,35 @@
 
 use askama::Template;
 use axum::{
-    extract::Path,
+    extract::{Path, Query},
     http::StatusCode,
     response::{IntoResponse, Redirect},
     Extension, Form,
 };
-use chrono::{NaiveDate, NaiveDateTime, Utc};
-use entity::{page, prelude::Page};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use entity::{
+    page, page_tag, post_draft,
+    prelude::{Page, PageTag, Tag, User},
+    sea_orm_active_enums::Role,
+    tag, user,
+};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter,
-    QueryOrder, Set,
+    sea_query, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait,
+    QueryFilter, QueryOrder, Select, Set,
 };
 use serde::Deserialize;
 
 use crate::{
-    admin::{is_valid_url, markdown::markdown_to_html, title_to_url},
+    admin::{
+        auth::require_role,
+        drafts::{delete_draft, draft},
+        page_url,
+        revisions::record_revision,
+        title_to_url,
+    },
+    announce::announce_post,
+    markdown::markdown_to_html,
+    search::{search_posts, SearchQuery, SearchResult},
     ErrorResponse, HtmlTemplate, ADMIN_URL_PREFIX,
 };
 
@@ -33,23 +47,130 @@ async fn post_by_id(
         .ok_or((StatusCode::NOT_FOUND, "post not found"))
 }
 
+/// Like `post_by_id`, but fails if `user` is not allowed to edit the post.
+/// Authors may only edit their own posts, while editors and admins may edit any post.
+pub(crate) async fn editable_post_by_id(
+    connection: &DatabaseConnection,
+    user: &user::Model,
+    id: i32,
+) -> Result<page::Model, ErrorResponse> {
+    let post = post_by_id(connection, id).await?;
+
+    if user.role == Role::Author && post.author_id != Some(user.id) {
+        return Err((StatusCode::FORBIDDEN, "insufficient permissions"));
+    }
+
+    Ok(post)
+}
+
+/// Parses a comma-separated list of tags into a list of unique tag names
+/// that are valid as URL components.
+fn parse_tags(tags: &str) -> Vec<String> {
+    let mut tag_names = Vec::new();
+
+    for tag_name in tags.split(',').map(|tag| title_to_url(tag.trim())) {
+        if !tag_name.is_empty() && !tag_names.contains(&tag_name) {
+            tag_names.push(tag_name);
+        }
+    }
+
+    tag_names
+}
+
+async fn set_post_tags(
+    connection: &DatabaseConnection,
+    post_id: i32,
+    tag_names: &[String],
+) -> Result<(), ErrorResponse> {
+    let error = (StatusCode::INTERNAL_SERVER_ERROR, "unable to save tags");
+
+    PageTag::delete_many()
+        .filter(page_tag::Column::PageId.eq(post_id))
+        .exec(connection)
+        .await
+        .map_err(|_| error)?;
+
+    for tag_name in tag_names {
+        let tag = match Tag::find()
+            .filter(tag::Column::Name.eq(tag_name.as_str()))
+            .one(connection)
+            .await
+            .map_err(|_| error)?
+        {
+            Some(tag) => tag,
+            None => tag::ActiveModel {
+                name: Set(tag_name.clone()),
+                ..Default::default()
+            }
+            .insert(connection)
+            .await
+            .map_err(|_| error)?,
+        };
+
+        page_tag::ActiveModel {
+            page_id: Set(post_id),
+            tag_id: Set(tag.id),
+        }
+        .insert(connection)
+        .await
+        .map_err(|_| error)?;
+    }
+
+    delete_unused_tags(connection).await
+}
+
+/// Removes tags that are no longer used by any page.
+async fn delete_unused_tags(connection: &DatabaseConnection) -> Result<(), ErrorResponse> {
+    Tag::delete_many()
+        .filter(
+            tag::Column::Id.not_in_subquery(
+                sea_query::Query::select()
+                    .column(page_tag::Column::TagId)
+                    .from(PageTag)
+                    .to_owned(),
+            ),
+        )
+        .exec(connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to delete tags"))?;
+
+    Ok(())
+}
+
+/// Returns the posts that `user` is allowed to edit.
+pub(crate) fn editable_posts(user: &user::Model) -> Select<Page> {
+    let posts = Page::find().filter(page::Column::IsPost.eq(true));
+
+    if user.role == Role::Author {
+        posts.filter(page::Column::AuthorId.eq(user.id))
+    } else {
+        posts
+    }
+}
+
 #[derive(Template)]
 #[template(path = "admin/posts.html")]
 struct PostsTemplate<'a> {
     admin_url_prefix: &'a str,
     title: &'a str,
-    posts: Vec<page::Model>,
+    current_role: Role,
+    query: String,
+    posts: Vec<(page::Model, Option<user::Model>)>,
+    search_results: Option<Vec<SearchResult>>,
+    now: NaiveDateTime,
 }
 
 pub(super) async fn get_posts(
     Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Query(SearchQuery { q: query }): Query<SearchQuery>,
 ) -> Result<impl IntoResponse, ErrorResponse> {
-    Ok(HtmlTemplate(PostsTemplate {
-        admin_url_prefix: ADMIN_URL_PREFIX,
-        title: "Posts",
-        posts: Page::find()
-            .filter(page::Column::IsPost.eq(true))
+    let posts = editable_posts(user);
+
+    let (posts, search_results) = if query.trim().is_empty() {
+        let posts = posts
             .order_by_desc(page::Column::Time)
+            .find_also_related(User)
             .all(database_connection)
             .await
             .map_err(|_| {
@@ -57,7 +178,23 @@ pub(super) async fn get_posts(
                     StatusCode::INTERNAL_SERVER_ERROR,
                     "unable to retrieve posts",
                 )
-            })?,
+            })?;
+
+        (posts, None)
+    } else {
+        let search_results = search_posts(database_connection, posts, &query).await?;
+
+        (Vec::new(), Some(search_results))
+    };
+
+    Ok(HtmlTemplate(PostsTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
+        title: "Posts",
+        query,
+        posts,
+        search_results,
+        now: Utc::now().naive_utc(),
     }))
 }
 
@@ -66,13 +203,26 @@ pub(super) async fn get_posts(
 struct PostTemplate<'a> {
     admin_url_prefix: &'a str,
     title: &'a str,
+    current_role: Role,
     post: page::Model,
+    input: PostInput,
+    draft: Option<post_draft::Model>,
+    is_recovered_draft: bool,
     is_new: bool,
+    can_publish: bool,
+}
+
+#[derive(Deserialize)]
+pub(super) struct PostQuery {
+    #[serde(default)]
+    recover_draft: bool,
 }
 
 pub(super) async fn get_post(
     Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
     Path(post_id): Path<String>,
+    Query(post_query): Query<PostQuery>,
 ) -> Result<impl IntoResponse, ErrorResponse> {
     let is_new = post_id == "new";
 
@@ -86,10 +236,15 @@ pub(super) async fn get_post(
             content_html: String::new(),
             is_post: true,
             is_published: false,
+            menu_order: None,
+            author_id: Some(user.id),
+            announced: None,
+            modified: None,
         }
     } else {
-        post_by_id(
+        editable_post_by_id(
             database_connection,
+            user,
             post_id
                 .parse()
                 .map_err(|_| (StatusCode::BAD_REQUEST, "invalid post ID"))?,
@@ -97,72 +252,152 @@ pub(super) async fn get_post(
         .await?
     };
 
+    let tags = if is_new {
+        Vec::new()
+    } else {
+        post.find_related(Tag)
+            .all(database_connection)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve tags"))?
+    };
+
+    let post_id = if is_new { None } else { Some(post.id) };
+
+    // Drafts are only offered for recovery if they are newer than the saved post,
+    // which might have been changed by another user in the meantime.
+    let draft = draft(database_connection, user, post_id)
+        .await?
+        .filter(|draft| post.modified.map_or(true, |time| draft.save_time > time));
+
+    let is_recovered_draft = draft.is_some() && post_query.recover_draft;
+
+    let input = match draft {
+        Some(ref draft) if is_recovered_draft => PostInput {
+            title: draft.title.clone(),
+            url: draft.url.clone(),
+            date: draft.date.clone(),
+            time: draft.time.clone(),
+            tags: draft.tags.clone(),
+            content: draft.content.clone(),
+        },
+        _ => PostInput {
+            title: post.title.clone(),
+            url: post.url.clone(),
+            date: if is_new {
+                String::new()
+            } else {
+                post.time.date().to_string()
+            },
+            time: if is_new {
+                String::new()
+            } else {
+                post.time.format("%H:%M").to_string()
+            },
+            tags: tags
+                .into_iter()
+                .map(|tag| tag.name)
+                .collect::<Vec<_>>()
+                .join(", "),
+            content: post.content_markdown.clone(),
+        },
+    };
+
     Ok(HtmlTemplate(PostTemplate {
         admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
         title: if is_new { "New post" } else { "Edit post" },
         post,
+        input,
+        draft,
+        is_recovered_draft,
         is_new,
+        can_publish: user.role >= Role::Editor,
     }))
 }
 
 #[derive(Debug, Deserialize)]
-pub(super) struct PostInput {
-    title: String,
-    url: String,
-    date: String,
-    content: String,
+pub(crate) struct PostInput {
+    pub(crate) title: String,
+    pub(crate) url: String,
+    pub(crate) date: String,
+    pub(crate) time: String,
+    pub(crate) tags: String,
+    pub(crate) content: String,
 }
 
-async fn save_post(
+/// Parses a post ID from a URL path, where `new` denotes a post that has not been saved yet.
+pub(super) fn parse_post_id(post_id: &str) -> Result<Option<i32>, ErrorResponse> {
+    if post_id == "new" {
+        Ok(None)
+    } else {
+        post_id
+            .parse()
+            .map(Some)
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid post ID"))
+    }
+}
+
+/// Creates a new post if `post_id` is `None`, and updates the existing post otherwise.
+pub(crate) async fn save_post(
     database_connection: &DatabaseConnection,
-    post_id: String,
+    user: &user::Model,
+    post_id: Option<i32>,
     post_input: &PostInput,
     set_is_published: Option<bool>,
-) -> Result<impl IntoResponse, ErrorResponse> {
-    let is_new = post_id == "new";
+) -> Result<page::Model, ErrorResponse> {
+    if set_is_published.is_some() {
+        require_role(user, Role::Editor)?;
+    }
 
-    let mut post = if is_new {
-        page::ActiveModel {
+    let mut post = match post_id {
+        None => page::ActiveModel {
             is_post: Set(true),
             is_published: Set(false),
+            author_id: Set(Some(user.id)),
             ..Default::default()
-        }
-    } else {
-        post_by_id(
-            database_connection,
-            post_id
-                .parse()
-                .map_err(|_| (StatusCode::BAD_REQUEST, "invalid post ID"))?,
-        )
-        .await?
-        .into()
+        },
+        Some(post_id) => editable_post_by_id(database_connection, user, post_id)
+            .await?
+            .into(),
     };
 
     post.title = Set(post_input.title.clone());
 
-    post.url = Set(if post_input.url.is_empty() {
-        title_to_url(&post_input.title)
-    } else if is_valid_url(&post_input.url) {
-        post_input.url.clone()
-    } else {
-        return Err((
-            StatusCode::UNPROCESSABLE_ENTITY,
-            "invalid URL, must contain only letters (a-z, A-Z), digits (0-9), and hyphens (-)",
-        ));
-    });
+    post.url = Set(page_url(&post_input.url, &post_input.title)?);
 
     post.time = Set(if post_input.date.is_empty() {
         Utc::now().naive_utc()
     } else {
-        NaiveDate::parse_from_str(&post_input.date, "%Y-%m-%d")
-            .map_err(|_| {
-                (
-                    StatusCode::UNPROCESSABLE_ENTITY,
-                    "invalid date, must be in format YYYY-MM-DD",
-                )
-            })?
-            .and_hms_opt(12, 0, 0)
-            .unwrap()
+        let date = NaiveDate::parse_from_str(&post_input.date, "%Y-%m-%d").map_err(|_| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid date, must be in format YYYY-MM-DD",
+            )
+        })?;
+
+        let now = Utc::now().naive_utc();
+
+        // Without a time, a post dated today is published right away,
+        // rather than being scheduled for later in the day.
+        // Some clients include seconds, which are accepted as well.
+        let time = if post_input.time.is_empty() {
+            if date == now.date() {
+                now.time()
+            } else {
+                NaiveTime::from_hms_opt(0, 0, 0).unwrap()
+            }
+        } else {
+            NaiveTime::parse_from_str(&post_input.time, "%H:%M")
+                .or_else(|_| NaiveTime::parse_from_str(&post_input.time, "%H:%M:%S"))
+                .map_err(|_| {
+                    (
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        "invalid time, must be in format HH:MM",
+                    )
+                })?
+        };
+
+        date.and_time(time)
     });
 
     post.content_markdown = Set(post_input.content.clone());
@@ -173,7 +408,9 @@ async fn save_post(
         post.is_published = Set(is_published);
     }
 
-    let post = if is_new {
+    post.modified = Set(Some(Utc::now().naive_utc()));
+
+    let post = if post_id.is_none() {
         post.insert(database_connection)
     } else {
         post.update(database_connection)
@@ -181,34 +418,97 @@ async fn save_post(
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save post"))?;
 
+    set_post_tags(database_connection, post.id, &parse_tags(&post_input.tags)).await?;
+
+    record_revision(database_connection, user, &post).await?;
+
+    // The draft is now obsolete.
+    delete_draft(database_connection, user, post_id).await?;
+
+    Ok(post)
+}
+
+async fn save_post_and_redirect(
+    database_connection: &DatabaseConnection,
+    user: &user::Model,
+    post_id: String,
+    post_input: &PostInput,
+    set_is_published: Option<bool>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let post = save_post(
+        database_connection,
+        user,
+        parse_post_id(&post_id)?,
+        post_input,
+        set_is_published,
+    )
+    .await?;
+
     Ok(Redirect::to(&format!(
         "{}/posts/{}",
         ADMIN_URL_PREFIX, post.id,
     )))
 }
 
+/// Publishes or unpublishes a post without otherwise changing it.
+pub(crate) async fn set_post_is_published(
+    database_connection: &DatabaseConnection,
+    user: &user::Model,
+    post_id: i32,
+    is_published: bool,
+) -> Result<page::Model, ErrorResponse> {
+    require_role(user, Role::Editor)?;
+
+    let mut post: page::ActiveModel = editable_post_by_id(database_connection, user, post_id)
+        .await?
+        .into();
+
+    post.is_published = Set(is_published);
+
+    post.update(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save post"))
+}
+
 pub(super) async fn post_save_post(
     Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
     Path(post_id): Path<String>,
     Form(ref post_input): Form<PostInput>,
 ) -> Result<impl IntoResponse, ErrorResponse> {
-    save_post(database_connection, post_id, post_input, None).await
+    save_post_and_redirect(database_connection, user, post_id, post_input, None).await
 }
 
 pub(super) async fn post_publish_post(
     Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
     Path(post_id): Path<String>,
     Form(ref post_input): Form<PostInput>,
 ) -> Result<impl IntoResponse, ErrorResponse> {
-    save_post(database_connection, post_id, post_input, Some(true)).await
+    let post = save_post(
+        database_connection,
+        user,
+        parse_post_id(&post_id)?,
+        post_input,
+        Some(true),
+    )
+    .await?;
+
+    announce_post(database_connection, &post).await?;
+
+    Ok(Redirect::to(&format!(
+        "{}/posts/{}",
+        ADMIN_URL_PREFIX, post.id,
+    )))
 }
 
 pub(super) async fn post_unpublish_post(
     Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
     Path(post_id): Path<String>,
     Form(ref post_input): Form<PostInput>,
 ) -> Result<impl IntoResponse, ErrorResponse> {
-    save_post(database_connection, post_id, post_input, Some(false)).await
+    save_post_and_redirect(database_connection, user, post_id, post_input, Some(false)).await
 }
 
 #[derive(Template)]
@@ -216,15 +516,18 @@ pub(super) async fn post_unpublish_post(
 struct DeletePostTemplate<'a> {
     admin_url_prefix: &'a str,
     title: &'a str,
+    current_role: Role,
     post: page::Model,
 }
 
 pub(super) async fn get_delete_post(
     Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
     Path(post_id): Path<String>,
 ) -> Result<impl IntoResponse, ErrorResponse> {
-    let post = post_by_id(
+    let post = editable_post_by_id(
         database_connection,
+        user,
         post_id
             .parse()
             .map_err(|_| (StatusCode::BAD_REQUEST, "invalid post ID"))?,
@@ -233,26 +536,135 @@ pub(super) async fn get_delete_post(
 
     Ok(HtmlTemplate(DeletePostTemplate {
         admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
         title: "Delete post",
         post,
     }))
 }
 
+pub(crate) async fn delete_post(
+    database_connection: &DatabaseConnection,
+    user: &user::Model,
+    post_id: i32,
+) -> Result<(), ErrorResponse> {
+    editable_post_by_id(database_connection, user, post_id)
+        .await?
+        .delete(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to delete post"))?;
+
+    // The post's tags were removed along with it.
+    delete_unused_tags(database_connection).await
+}
+
 pub(super) async fn post_delete_post(
     Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
     Path(post_id): Path<String>,
 ) -> Result<impl IntoResponse, ErrorResponse> {
-    let post = post_by_id(
+    delete_post(
         database_connection,
+        user,
         post_id
             .parse()
             .map_err(|_| (StatusCode::BAD_REQUEST, "invalid post ID"))?,
     )
     .await?;
 
-    post.delete(database_connection)
+    Ok(Redirect::to(&format!("{}/posts", ADMIN_URL_PREFIX)))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::Json;
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::Database;
+
+    use super::*;
+    use crate::admin::drafts::post_save_draft;
+
+    fn post_input(tags: &str) -> PostInput {
+        PostInput {
+            title: "Hello".to_owned(),
+            url: String::new(),
+            date: "2023-01-15".to_owned(),
+            time: "10:30".to_owned(),
+            tags: tags.to_owned(),
+            content: "Text".to_owned(),
+        }
+    }
+
+    async fn user(connection: &DatabaseConnection, name: &str) -> user::Model {
+        user::ActiveModel {
+            name: Set(name.to_owned()),
+            password_hash: Set(String::new()),
+            role: Set(Role::Editor),
+            ..Default::default()
+        }
+        .insert(connection)
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to delete post"))?;
+        .unwrap()
+    }
 
-    Ok(Redirect::to(&format!("{}/posts", ADMIN_URL_PREFIX)))
+    async fn save_draft(connection: &DatabaseConnection, user: &user::Model, post_id: i32) {
+        post_save_draft(
+            Extension(connection.clone()),
+            Extension(user.clone()),
+            Path(post_id.to_string()),
+            Json(post_input("draft")),
+        )
+        .await
+        .unwrap();
+    }
+
+    /// Returns whether the post editor offers `user` to recover a draft.
+    async fn offers_draft(
+        connection: &DatabaseConnection,
+        user: &user::Model,
+        post_id: i32,
+    ) -> bool {
+        let response = get_post(
+            Extension(connection.clone()),
+            Extension(user.clone()),
+            Path(post_id.to_string()),
+            Query(PostQuery {
+                recover_draft: false,
+            }),
+        )
+        .await
+        .unwrap()
+        .into_response();
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+        String::from_utf8(body.to_vec())
+            .unwrap()
+            .contains("There is an unsaved draft")
+    }
+
+    #[tokio::test]
+    async fn draft_is_offered_only_if_newer_than_last_save() {
+        let connection = Database::connect("sqlite::memory:").await.unwrap();
+
+        Migrator::up(&connection, None).await.unwrap();
+
+        let jane = user(&connection, "jane").await;
+        let joe = user(&connection, "joe").await;
+
+        let post = save_post(&connection, &jane, None, &post_input(""), None)
+            .await
+            .unwrap();
+
+        save_draft(&connection, &jane, post.id).await;
+        assert!(offers_draft(&connection, &jane, post.id).await);
+
+        // Changing only the tags records no revision, but is a save all the same.
+        save_post(&connection, &joe, Some(post.id), &post_input("news"), None)
+            .await
+            .unwrap();
+        assert!(!offers_draft(&connection, &jane, post.id).await);
+
+        save_draft(&connection, &jane, post.id).await;
+        assert!(offers_draft(&connection, &jane, post.id).await);
+    }
 }
@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use askama::Template;
+use axum::{response::IntoResponse, Extension, Form};
+use sea_orm::DatabaseConnection;
+use serde::Deserialize;
+
+use crate::{markdown::markdown_to_html, settings, ErrorResponse, HtmlTemplate};
+
+/// The part of the site that the previewed content will be shown in,
+/// which determines the element that the rendered HTML is wrapped in.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum PreviewContext {
+    #[default]
+    Post,
+    Header,
+    Footer,
+}
+
+#[derive(Template)]
+#[template(path = "admin/preview.html")]
+struct PreviewTemplate {
+    css: String,
+    context: PreviewContext,
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct PreviewInput {
+    markdown: String,
+    #[serde(default)]
+    context: PreviewContext,
+}
+
+/// Renders Markdown as a standalone HTML document styled with the site's CSS,
+/// for display in the editors' preview panes.
+pub(super) async fn post_preview(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Form(preview_input): Form<PreviewInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    Ok(HtmlTemplate(PreviewTemplate {
+        css: settings(database_connection).await?.css,
+        context: preview_input.context,
+        content: markdown_to_html(&preview_input.markdown),
+    }))
+}
@@ -0,0 +1,199 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use askama::Template;
+use axum::{
+    extract::{Host, Path},
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    Extension, Form,
+};
+use chrono::{Duration, NaiveDateTime, Utc};
+use entity::{page, prelude::PreviewLink, preview_link, sea_orm_active_enums::Role, user};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter,
+    QueryOrder, Set,
+};
+use serde::Deserialize;
+
+use crate::{
+    admin::posts::editable_post_by_id, random_token, settings, site::base_url, ErrorResponse,
+    HtmlTemplate, ADMIN_URL_PREFIX,
+};
+
+/// Links that should never expire are created without an expiry instead.
+const MAX_EXPIRY_DAYS: i64 = 3650;
+
+struct PreviewLinkInfo {
+    link: preview_link::Model,
+    url: String,
+    is_expired: bool,
+}
+
+#[derive(Template)]
+#[template(path = "admin/preview_links.html")]
+struct PreviewLinksTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    post: page::Model,
+    links: Vec<PreviewLinkInfo>,
+}
+
+pub(super) async fn get_preview_links(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Host(host): Host,
+    Path(post_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let post = editable_post_by_id(
+        database_connection,
+        user,
+        post_id
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid post ID"))?,
+    )
+    .await?;
+
+    let base_url = base_url(&settings(database_connection).await?, &host);
+    let now = Utc::now().naive_utc();
+
+    let links = post
+        .find_related(PreviewLink)
+        .order_by_desc(preview_link::Column::Created)
+        .all(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve preview links",
+            )
+        })?
+        .into_iter()
+        .map(|link| PreviewLinkInfo {
+            url: format!("{}/preview/{}", base_url, link.token),
+            is_expired: link.expires.map_or(false, |expires| expires <= now),
+            link,
+        })
+        .collect();
+
+    Ok(HtmlTemplate(PreviewLinksTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
+        title: "Preview links",
+        post,
+        links,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct PreviewLinkInput {
+    expires_in_days: String,
+}
+
+pub(super) async fn post_create_preview_link(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(post_id): Path<String>,
+    Form(ref preview_link_input): Form<PreviewLinkInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let post = editable_post_by_id(
+        database_connection,
+        user,
+        post_id
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid post ID"))?,
+    )
+    .await?;
+
+    let now = Utc::now().naive_utc();
+
+    let expires: Option<NaiveDateTime> = if preview_link_input.expires_in_days.is_empty() {
+        None
+    } else {
+        let days: i64 = preview_link_input
+            .expires_in_days
+            .parse()
+            .ok()
+            .filter(|days| (1..=MAX_EXPIRY_DAYS).contains(days))
+            .ok_or((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid expiry, must be between 1 and 3650 days",
+            ))?;
+
+        Some(now + Duration::days(days))
+    };
+
+    // Expired links are useless, so this is a good opportunity to clean them up.
+    PreviewLink::delete_many()
+        .filter(preview_link::Column::Expires.lte(now))
+        .exec(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to delete expired preview links",
+            )
+        })?;
+
+    preview_link::ActiveModel {
+        page_id: Set(post.id),
+        token: Set(random_token()),
+        created: Set(now),
+        expires: Set(expires),
+        ..Default::default()
+    }
+    .insert(database_connection)
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to create preview link",
+        )
+    })?;
+
+    Ok(Redirect::to(&format!(
+        "{}/posts/{}/preview-links",
+        ADMIN_URL_PREFIX, post.id,
+    )))
+}
+
+pub(super) async fn post_revoke_preview_link(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path((post_id, link_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let post = editable_post_by_id(
+        database_connection,
+        user,
+        post_id
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid post ID"))?,
+    )
+    .await?;
+
+    let link_id: i32 = link_id
+        .parse()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "invalid preview link ID"))?;
+
+    let result = PreviewLink::delete_many()
+        .filter(preview_link::Column::Id.eq(link_id))
+        .filter(preview_link::Column::PageId.eq(post.id))
+        .exec(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to revoke preview link",
+            )
+        })?;
+
+    if result.rows_affected == 0 {
+        return Err((StatusCode::NOT_FOUND, "preview link not found"));
+    }
+
+    Ok(Redirect::to(&format!(
+        "{}/posts/{}/preview-links",
+        ADMIN_URL_PREFIX, post.id,
+    )))
+}
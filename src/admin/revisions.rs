@@ -0,0 +1,270 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use askama::Template;
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    Extension,
+};
+use chrono::Utc;
+use entity::{
+    page, page_revision,
+    prelude::{PageRevision, User},
+    sea_orm_active_enums::Role,
+    user,
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+};
+use serde::Deserialize;
+use similar::{ChangeTag, TextDiff};
+
+use crate::{
+    admin::posts::editable_post_by_id, markdown::markdown_to_html, ErrorResponse, HtmlTemplate,
+    ADMIN_URL_PREFIX,
+};
+
+/// Number of unchanged lines shown around each change in a diff.
+const DIFF_CONTEXT_LINES: usize = 3;
+
+async fn latest_revision(
+    connection: &DatabaseConnection,
+    post_id: i32,
+) -> Result<Option<page_revision::Model>, ErrorResponse> {
+    PageRevision::find()
+        .filter(page_revision::Column::PageId.eq(post_id))
+        .order_by_desc(page_revision::Column::Id)
+        .one(connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve revision",
+            )
+        })
+}
+
+/// Records the current title and content of `post` as a new revision,
+/// unless they are identical to those of the most recent revision.
+pub(super) async fn record_revision(
+    connection: &DatabaseConnection,
+    user: &user::Model,
+    post: &page::Model,
+) -> Result<(), ErrorResponse> {
+    if let Some(latest_revision) = latest_revision(connection, post.id).await? {
+        if latest_revision.title == post.title
+            && latest_revision.content_markdown == post.content_markdown
+        {
+            return Ok(());
+        }
+    }
+
+    page_revision::ActiveModel {
+        page_id: Set(post.id),
+        time: Set(Utc::now().naive_utc()),
+        title: Set(post.title.clone()),
+        content_markdown: Set(post.content_markdown.clone()),
+        author_id: Set(Some(user.id)),
+        ..Default::default()
+    }
+    .insert(connection)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save revision"))?;
+
+    Ok(())
+}
+
+struct DiffLine {
+    tag: ChangeTag,
+    text: String,
+}
+
+impl DiffLine {
+    fn class(&self) -> &'static str {
+        match self.tag {
+            ChangeTag::Equal => "equal",
+            ChangeTag::Insert => "insert",
+            ChangeTag::Delete => "delete",
+        }
+    }
+}
+
+/// Computes a line diff between `old` and `new`, grouped into hunks
+/// that contain the changed lines and some surrounding context.
+fn diff_hunks(old: &str, new: &str) -> Vec<Vec<DiffLine>> {
+    // Otherwise, a final line without a line break would be considered
+    // different from the same line once more lines are added after it.
+    let with_final_line_break = |text: &str| {
+        if text.ends_with('\n') {
+            text.to_owned()
+        } else {
+            format!("{}\n", text)
+        }
+    };
+
+    let old = with_final_line_break(old);
+    let new = with_final_line_break(new);
+
+    let diff = TextDiff::from_lines(&old, &new);
+
+    diff.grouped_ops(DIFF_CONTEXT_LINES)
+        .iter()
+        .map(|group| {
+            group
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| DiffLine {
+                    tag: change.tag(),
+                    text: change.value().trim_end_matches(['\r', '\n']).to_owned(),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[derive(Template)]
+#[template(path = "admin/revisions.html")]
+struct RevisionsTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    post: page::Model,
+    revisions: Vec<(page_revision::Model, Option<user::Model>)>,
+    from: Option<i32>,
+    to: Option<i32>,
+    title_change: Option<(String, String)>,
+    hunks: Vec<Vec<DiffLine>>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct CompareQuery {
+    from: Option<i32>,
+    to: Option<i32>,
+}
+
+pub(super) async fn get_revisions(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(post_id): Path<String>,
+    Query(compare_query): Query<CompareQuery>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let post = editable_post_by_id(
+        database_connection,
+        user,
+        post_id
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid post ID"))?,
+    )
+    .await?;
+
+    let revisions = PageRevision::find()
+        .filter(page_revision::Column::PageId.eq(post.id))
+        .order_by_desc(page_revision::Column::Id)
+        .find_also_related(User)
+        .all(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve revisions",
+            )
+        })?;
+
+    // By default, compare the two most recent revisions.
+    let from = compare_query
+        .from
+        .or_else(|| revisions.get(1).map(|(revision, _)| revision.id));
+    let to = compare_query
+        .to
+        .or_else(|| revisions.first().map(|(revision, _)| revision.id));
+
+    let revision_by_id = |id| {
+        revisions
+            .iter()
+            .map(|(revision, _)| revision)
+            .find(|revision| revision.id == id)
+            .ok_or((StatusCode::NOT_FOUND, "revision not found"))
+    };
+
+    let (title_change, hunks) = match (from, to) {
+        (Some(from), Some(to)) => {
+            let from = revision_by_id(from)?;
+            let to = revision_by_id(to)?;
+
+            (
+                if from.title == to.title {
+                    None
+                } else {
+                    Some((from.title.clone(), to.title.clone()))
+                },
+                diff_hunks(&from.content_markdown, &to.content_markdown),
+            )
+        }
+        _ => (None, Vec::new()),
+    };
+
+    Ok(HtmlTemplate(RevisionsTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
+        title: "Post history",
+        post,
+        revisions,
+        from,
+        to,
+        title_change,
+        hunks,
+    }))
+}
+
+pub(super) async fn post_restore_revision(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path((post_id, revision_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let post = editable_post_by_id(
+        database_connection,
+        user,
+        post_id
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid post ID"))?,
+    )
+    .await?;
+
+    let revision = PageRevision::find_by_id(
+        revision_id
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid revision ID"))?,
+    )
+    .filter(page_revision::Column::PageId.eq(post.id))
+    .one(database_connection)
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to retrieve revision",
+        )
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "revision not found"))?;
+
+    let mut post: page::ActiveModel = post.into();
+
+    post.title = Set(revision.title);
+    post.content_html = Set(markdown_to_html(&revision.content_markdown));
+    post.content_markdown = Set(revision.content_markdown);
+    post.modified = Set(Some(Utc::now().naive_utc()));
+
+    let post = post
+        .update(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save post"))?;
+
+    // Restoring is itself recorded, so that it can be undone.
+    record_revision(database_connection, user, &post).await?;
+
+    Ok(Redirect::to(&format!(
+        "{}/posts/{}",
+        ADMIN_URL_PREFIX, post.id,
+    )))
+}
@@ -7,11 +7,13 @@ use axum::{
     response::{IntoResponse, Redirect},
     Extension, Form,
 };
+use entity::{sea_orm_active_enums::Role, user};
 use sea_orm::{ActiveModelTrait, DatabaseConnection, Set};
 use serde::Deserialize;
 
 use crate::{
-    admin::markdown::markdown_to_html, settings, ErrorResponse, HtmlTemplate, ADMIN_URL_PREFIX,
+    admin::is_valid_site_url, markdown::markdown_to_html, settings, ErrorResponse, HtmlTemplate,
+    ADMIN_URL_PREFIX,
 };
 
 #[derive(Template)]
@@ -19,14 +21,17 @@ use crate::{
 struct HeaderTemplate<'a> {
     admin_url_prefix: &'a str,
     title: &'a str,
+    current_role: Role,
     header: String,
 }
 
 pub(super) async fn get_header(
     Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
 ) -> Result<impl IntoResponse, ErrorResponse> {
     Ok(HtmlTemplate(HeaderTemplate {
         admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
         title: "Header",
         header: settings(database_connection).await?.header_markdown,
     }))
@@ -60,14 +65,17 @@ pub(super) async fn post_header(
 struct FooterTemplate<'a> {
     admin_url_prefix: &'a str,
     title: &'a str,
+    current_role: Role,
     footer: String,
 }
 
 pub(super) async fn get_footer(
     Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
 ) -> Result<impl IntoResponse, ErrorResponse> {
     Ok(HtmlTemplate(FooterTemplate {
         admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
         title: "Footer",
         footer: settings(database_connection).await?.footer_markdown,
     }))
@@ -101,14 +109,17 @@ pub(super) async fn post_footer(
 struct CssTemplate<'a> {
     admin_url_prefix: &'a str,
     title: &'a str,
+    current_role: Role,
     css: String,
 }
 
 pub(super) async fn get_css(
     Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
 ) -> Result<impl IntoResponse, ErrorResponse> {
     Ok(HtmlTemplate(CssTemplate {
         admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
         title: "CSS",
         css: settings(database_connection).await?.css,
     }))
@@ -140,14 +151,17 @@ pub(super) async fn post_css(
 struct JavascriptTemplate<'a> {
     admin_url_prefix: &'a str,
     title: &'a str,
+    current_role: Role,
     javascript: String,
 }
 
 pub(super) async fn get_javascript(
     Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
 ) -> Result<impl IntoResponse, ErrorResponse> {
     Ok(HtmlTemplate(JavascriptTemplate {
         admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
         title: "JavaScript",
         javascript: settings(database_connection).await?.javascript,
     }))
@@ -181,30 +195,60 @@ pub(super) async fn post_javascript(
 struct SettingsTemplate<'a> {
     admin_url_prefix: &'a str,
     title: &'a str,
+    current_role: Role,
     settings: settings::Model,
 }
 
 pub(super) async fn get_settings(
     Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
 ) -> Result<impl IntoResponse, ErrorResponse> {
     Ok(HtmlTemplate(SettingsTemplate {
         admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
         title: "Settings",
         settings: settings(database_connection).await?,
     }))
 }
 
 #[derive(Debug, Deserialize)]
-pub(super) struct SettingsInput {
-    posts_per_page: String,
+pub(crate) struct SettingsInput {
+    pub(crate) site_title: String,
+    pub(crate) site_url: String,
+    pub(crate) posts_per_page: String,
+    pub(crate) robots_txt: String,
+    pub(crate) spam_min_fill_seconds: String,
+    pub(crate) spam_max_submissions_per_hour: String,
+    pub(crate) spam_score_threshold: String,
 }
 
-pub(super) async fn post_settings(
-    Extension(ref database_connection): Extension<DatabaseConnection>,
-    Form(ref settings_input): Form<SettingsInput>,
-) -> Result<impl IntoResponse, ErrorResponse> {
+/// Parses a setting for which zero means "disabled".
+fn parse_non_negative(value: &str, error: &'static str) -> Result<i32, ErrorResponse> {
+    match value.parse() {
+        Ok(value) if value >= 0 => Ok(value),
+        _ => Err((StatusCode::UNPROCESSABLE_ENTITY, error)),
+    }
+}
+
+pub(crate) async fn save_settings(
+    database_connection: &DatabaseConnection,
+    settings_input: &SettingsInput,
+) -> Result<settings::Model, ErrorResponse> {
     let mut settings: settings::ActiveModel = settings(database_connection).await?.into();
 
+    settings.site_title = Set(settings_input.site_title.trim().to_owned());
+
+    let site_url = settings_input.site_url.trim().trim_end_matches('/');
+
+    settings.site_url = Set(if site_url.is_empty() || is_valid_site_url(site_url) {
+        site_url.to_owned()
+    } else {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid site URL, must start with 'http://' or 'https://'",
+        ));
+    });
+
     settings.posts_per_page = Set(match settings_input.posts_per_page.parse() {
         Ok(posts_per_page) if posts_per_page > 0 => posts_per_page,
         _ => {
@@ -215,10 +259,34 @@ pub(super) async fn post_settings(
         }
     });
 
+    settings.robots_txt = Set(settings_input.robots_txt.clone());
+
+    settings.spam_min_fill_seconds = Set(parse_non_negative(
+        &settings_input.spam_min_fill_seconds,
+        "invalid 'minimum fill time' value, must be a non-negative integer",
+    )?);
+
+    settings.spam_max_submissions_per_hour = Set(parse_non_negative(
+        &settings_input.spam_max_submissions_per_hour,
+        "invalid 'submissions per hour' value, must be a non-negative integer",
+    )?);
+
+    settings.spam_score_threshold = Set(parse_non_negative(
+        &settings_input.spam_score_threshold,
+        "invalid 'spam score threshold' value, must be a non-negative integer",
+    )?);
+
     settings
         .update(database_connection)
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save settings"))?;
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save settings"))
+}
+
+pub(super) async fn post_settings(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Form(ref settings_input): Form<SettingsInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    save_settings(database_connection, settings_input).await?;
 
     Ok(Redirect::to(&format!("{}/settings", ADMIN_URL_PREFIX)))
 }
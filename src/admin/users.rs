@@ -0,0 +1,249 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use askama::Template;
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    Extension, Form,
+};
+use entity::{prelude::User, sea_orm_active_enums::Role, user};
+use sea_orm::{
+    ActiveEnum, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, Set,
+};
+use serde::Deserialize;
+
+use crate::{
+    admin::auth::{hash_password, validate_password},
+    ErrorResponse, HtmlTemplate, ADMIN_URL_PREFIX,
+};
+
+async fn user_by_id(
+    connection: &DatabaseConnection,
+    id: i32,
+) -> Result<user::Model, ErrorResponse> {
+    User::find_by_id(id)
+        .one(connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve user"))?
+        .ok_or((StatusCode::NOT_FOUND, "user not found"))
+}
+
+#[derive(Template)]
+#[template(path = "admin/users.html")]
+struct UsersTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    users: Vec<user::Model>,
+}
+
+pub(super) async fn get_users(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    Ok(HtmlTemplate(UsersTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
+        title: "Users",
+        users: User::find()
+            .order_by_asc(user::Column::Name)
+            .all(database_connection)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "unable to retrieve users",
+                )
+            })?,
+    }))
+}
+
+#[derive(Template)]
+#[template(path = "admin/user.html")]
+struct UserTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    user: user::Model,
+    is_new: bool,
+    is_current_user: bool,
+}
+
+pub(super) async fn get_user(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref current_user): Extension<user::Model>,
+    Path(user_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let is_new = user_id == "new";
+
+    let user = if is_new {
+        user::Model {
+            id: 0,
+            name: String::new(),
+            password_hash: String::new(),
+            role: Role::Author,
+        }
+    } else {
+        user_by_id(
+            database_connection,
+            user_id
+                .parse()
+                .map_err(|_| (StatusCode::BAD_REQUEST, "invalid user ID"))?,
+        )
+        .await?
+    };
+
+    Ok(HtmlTemplate(UserTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: current_user.role,
+        title: if is_new { "New user" } else { "Edit user" },
+        is_current_user: !is_new && user.id == current_user.id,
+        user,
+        is_new,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct UserInput {
+    name: String,
+    role: String,
+    password: String,
+}
+
+pub(super) async fn post_save_user(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref current_user): Extension<user::Model>,
+    Path(user_id): Path<String>,
+    Form(ref user_input): Form<UserInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let is_new = user_id == "new";
+
+    let mut user: user::ActiveModel = if is_new {
+        Default::default()
+    } else {
+        user_by_id(
+            database_connection,
+            user_id
+                .parse()
+                .map_err(|_| (StatusCode::BAD_REQUEST, "invalid user ID"))?,
+        )
+        .await?
+        .into()
+    };
+
+    let name = user_input.name.trim();
+
+    if name.is_empty() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "user name must not be empty",
+        ));
+    }
+
+    let mut users_with_name = User::find().filter(user::Column::Name.eq(name));
+
+    if !is_new {
+        users_with_name = users_with_name.filter(user::Column::Id.ne(user.id.clone().unwrap()));
+    }
+
+    if users_with_name
+        .count(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve users",
+            )
+        })?
+        > 0
+    {
+        return Err((StatusCode::CONFLICT, "a user with this name already exists"));
+    }
+
+    user.name = Set(name.to_owned());
+
+    let role = Role::try_from_value(&user_input.role)
+        .map_err(|_| (StatusCode::UNPROCESSABLE_ENTITY, "invalid role"))?;
+
+    // Prevent admins from accidentally locking themselves out.
+    if !is_new && user.id.clone().unwrap() == current_user.id && role != current_user.role {
+        return Err((StatusCode::FORBIDDEN, "you cannot change your own role"));
+    }
+
+    user.role = Set(role);
+
+    if is_new || !user_input.password.is_empty() {
+        validate_password(&user_input.password)?;
+        user.password_hash = Set(hash_password(&user_input.password)?);
+    }
+
+    let user = if is_new {
+        user.insert(database_connection)
+    } else {
+        user.update(database_connection)
+    }
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save user"))?;
+
+    Ok(Redirect::to(&format!(
+        "{}/users/{}",
+        ADMIN_URL_PREFIX, user.id,
+    )))
+}
+
+#[derive(Template)]
+#[template(path = "admin/delete_user.html")]
+struct DeleteUserTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    user: user::Model,
+}
+
+pub(super) async fn get_delete_user(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref current_user): Extension<user::Model>,
+    Path(user_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let user = user_by_id(
+        database_connection,
+        user_id
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid user ID"))?,
+    )
+    .await?;
+
+    Ok(HtmlTemplate(DeleteUserTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: current_user.role,
+        title: "Delete user",
+        user,
+    }))
+}
+
+pub(super) async fn post_delete_user(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref current_user): Extension<user::Model>,
+    Path(user_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let user = user_by_id(
+        database_connection,
+        user_id
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid user ID"))?,
+    )
+    .await?;
+
+    if user.id == current_user.id {
+        return Err((StatusCode::FORBIDDEN, "you cannot delete your own account"));
+    }
+
+    user.delete(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to delete user"))?;
+
+    Ok(Redirect::to(&format!("{}/users", ADMIN_URL_PREFIX)))
+}
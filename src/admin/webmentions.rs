@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use askama::Template;
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    Extension,
+};
+use entity::{
+    page,
+    prelude::{Page, Webmention},
+    sea_orm_active_enums::Role,
+    user, webmention,
+};
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryOrder, Set};
+
+use crate::{ErrorResponse, HtmlTemplate, ADMIN_URL_PREFIX};
+
+async fn webmention_by_id(
+    connection: &DatabaseConnection,
+    id: String,
+) -> Result<webmention::Model, ErrorResponse> {
+    Webmention::find_by_id(
+        id.parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid mention ID"))?,
+    )
+    .one(connection)
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to retrieve mention",
+        )
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "mention not found"))
+}
+
+#[derive(Template)]
+#[template(path = "admin/webmentions.html")]
+struct WebmentionsTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    mentions: Vec<(webmention::Model, Option<page::Model>)>,
+}
+
+pub(super) async fn get_webmentions(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    Ok(HtmlTemplate(WebmentionsTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
+        title: "Mentions",
+        // Mentions awaiting moderation come first.
+        mentions: Webmention::find()
+            .order_by_asc(webmention::Column::IsApproved)
+            .order_by_desc(webmention::Column::Received)
+            .find_also_related(Page)
+            .all(database_connection)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "unable to retrieve mentions",
+                )
+            })?,
+    }))
+}
+
+pub(super) async fn post_approve_webmention(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(webmention_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let mut mention: webmention::ActiveModel = webmention_by_id(database_connection, webmention_id)
+        .await?
+        .into();
+
+    mention.is_approved = Set(true);
+
+    mention
+        .update(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save mention"))?;
+
+    Ok(Redirect::to(&format!("{}/webmentions", ADMIN_URL_PREFIX)))
+}
+
+pub(super) async fn post_delete_webmention(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(webmention_id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    webmention_by_id(database_connection, webmention_id)
+        .await?
+        .delete(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to delete mention",
+            )
+        })?;
+
+    Ok(Redirect::to(&format!("{}/webmentions", ADMIN_URL_PREFIX)))
+}
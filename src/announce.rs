@@ -0,0 +1,222 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+//! Notifies other sites of new posts, via Webmention and ActivityPub.
+//! Posts that are published with a time in the future are announced
+//! by a background job once that time arrives.
+
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use entity::{page, prelude::Page};
+use sea_orm::{sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+
+use crate::{
+    activitypub::deliver_post, settings, site::published_pages, webmention::send_webmentions,
+    ErrorResponse,
+};
+
+/// How often the background job checks for scheduled posts that are due.
+const SCHEDULE_INTERVAL_SECONDS: u64 = 60;
+
+/// Announces `post` if it is published, its time has arrived,
+/// and it hasn't been announced before. Otherwise, nothing happens.
+pub(crate) async fn announce_post(
+    connection: &DatabaseConnection,
+    post: &page::Model,
+) -> Result<(), ErrorResponse> {
+    let now = Utc::now().naive_utc();
+
+    if !post.is_post || !post.is_published || post.time > now || post.announced.is_some() {
+        return Ok(());
+    }
+
+    // Other sites store the post's address, so it can't be derived from the request's host,
+    // which might not be the site's public address. The background job announces the post
+    // once the site URL has been configured.
+    if settings(connection).await?.site_url.is_empty() {
+        return Ok(());
+    }
+
+    // Marking the post before announcing it ensures that it is announced only once,
+    // even if it is published while the background job is running.
+    let result = Page::update_many()
+        .col_expr(page::Column::Announced, Expr::value(now))
+        .filter(page::Column::Id.eq(post.id))
+        .filter(page::Column::Announced.is_null())
+        .exec(connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save post"))?;
+
+    if result.rows_affected == 0 {
+        return Ok(());
+    }
+
+    send_webmentions(connection, post).await?;
+
+    deliver_post(connection, post).await
+}
+
+async fn announce_due_posts(connection: &DatabaseConnection) -> Result<(), ErrorResponse> {
+    // Posts can't be announced before the site URL has been configured.
+    if settings(connection).await?.site_url.is_empty() {
+        return Ok(());
+    }
+
+    let posts = published_pages()
+        .filter(page::Column::IsPost.eq(true))
+        .filter(page::Column::Announced.is_null())
+        .all(connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve posts",
+            )
+        })?;
+
+    for post in posts {
+        announce_post(connection, &post).await?;
+    }
+
+    Ok(())
+}
+
+/// Periodically announces scheduled posts whose time has arrived. Never returns.
+pub(crate) async fn announce_scheduled_posts(connection: DatabaseConnection) {
+    let mut interval = tokio::time::interval(Duration::from_secs(SCHEDULE_INTERVAL_SECONDS));
+
+    loop {
+        interval.tick().await;
+
+        // There is no one to report errors to, and the next run will try again.
+        let _ = announce_due_posts(&connection).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::{prelude::Settings, settings};
+    use migration::{Migrator, MigratorTrait};
+    use rsa::{pkcs8::EncodePrivateKey, pkcs8::LineEnding, RsaPrivateKey};
+    use sea_orm::{ActiveModelTrait, ConnectionTrait, Database, DbBackend, Set, Statement};
+
+    use super::*;
+
+    async fn post_by_url(connection: &DatabaseConnection, url: &str) -> page::Model {
+        Page::find()
+            .filter(page::Column::Url.eq(url))
+            .one(connection)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    async fn configure_site(connection: &DatabaseConnection) {
+        let mut settings: settings::ActiveModel = Settings::find()
+            .one(connection)
+            .await
+            .unwrap()
+            .unwrap()
+            .into();
+        settings.site_url = Set("https://blog.example.com".to_owned());
+        settings.actor_private_key = Set(RsaPrivateKey::new(&mut rand::thread_rng(), 1024)
+            .unwrap()
+            .to_pkcs8_pem(LineEnding::LF)
+            .unwrap()
+            .to_string());
+        settings.update(connection).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn announce_post_waits_for_site_url() {
+        let connection = Database::connect("sqlite::memory:").await.unwrap();
+
+        Migrator::up(&connection, None).await.unwrap();
+
+        page::ActiveModel {
+            time: Set(Utc::now().naive_utc()),
+            title: Set("Post".to_owned()),
+            url: Set("post".to_owned()),
+            content_markdown: Set(String::new()),
+            content_html: Set(String::new()),
+            is_post: Set(true),
+            is_published: Set(true),
+            ..Default::default()
+        }
+        .insert(&connection)
+        .await
+        .unwrap();
+
+        announce_post(&connection, &post_by_url(&connection, "post").await)
+            .await
+            .unwrap();
+        assert!(post_by_url(&connection, "post").await.announced.is_none());
+
+        configure_site(&connection).await;
+
+        announce_due_posts(&connection).await.unwrap();
+        assert!(post_by_url(&connection, "post").await.announced.is_some());
+    }
+
+    #[tokio::test]
+    async fn migration_leaves_scheduled_posts_to_be_announced() {
+        let connection = Database::connect("sqlite::memory:").await.unwrap();
+
+        // Apply the migrations up to the one that adds the announcement time,
+        // and create posts the way they were stored before it.
+        let steps = Migrator::migrations()
+            .iter()
+            .position(|migration| migration.name() == "m20230119_000001_add_page_announced")
+            .unwrap();
+
+        Migrator::up(&connection, Some(steps as u32)).await.unwrap();
+
+        for (url, time) in [
+            ("published", "2020-01-01 12:00:00"),
+            ("scheduled", "2999-01-01 12:00:00"),
+        ] {
+            connection
+                .execute(Statement::from_sql_and_values(
+                    DbBackend::Sqlite,
+                    "INSERT INTO page (time, title, url, content_markdown, content_html, is_post, is_published) \
+                     VALUES (?, 'Post', ?, '', '', TRUE, TRUE)",
+                    [time.into(), url.into()],
+                ))
+                .await
+                .unwrap();
+        }
+
+        Migrator::up(&connection, None).await.unwrap();
+
+        assert!(post_by_url(&connection, "published")
+            .await
+            .announced
+            .is_some());
+        assert!(post_by_url(&connection, "scheduled")
+            .await
+            .announced
+            .is_none());
+
+        configure_site(&connection).await;
+
+        // Not yet due.
+        announce_due_posts(&connection).await.unwrap();
+        assert!(post_by_url(&connection, "scheduled")
+            .await
+            .announced
+            .is_none());
+
+        // The scheduled time arrives.
+        let mut post: page::ActiveModel = post_by_url(&connection, "scheduled").await.into();
+        post.time = Set(Utc::now().naive_utc());
+        post.update(&connection).await.unwrap();
+
+        announce_due_posts(&connection).await.unwrap();
+        assert!(post_by_url(&connection, "scheduled")
+            .await
+            .announced
+            .is_some());
+    }
+}
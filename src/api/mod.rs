@@ -0,0 +1,270 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+mod pages;
+mod posts;
+mod settings;
+
+use axum::{
+    async_trait,
+    extract::FromRequest,
+    http::{header::AUTHORIZATION, HeaderValue, Method, Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::Utc;
+use entity::{
+    api_token,
+    prelude::{ApiToken, User},
+    sea_orm_active_enums::ApiTokenScope,
+    user,
+};
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::{
+    admin::{api_tokens::hash_api_token, auth::session_user},
+    ErrorResponse,
+};
+
+pub(super) const API_URL_PREFIX: &str = "/api/v1";
+
+pub(super) fn router() -> Router {
+    // Permissions for individual posts are checked by the post functions.
+    let posts_routes = Router::new()
+        .route(
+            "/posts",
+            get(posts::get_posts).post(posts::post_create_post),
+        )
+        .route(
+            "/posts/:post_id",
+            get(posts::get_post)
+                .put(posts::put_post)
+                .delete(posts::delete_post),
+        )
+        .route("/posts/:post_id/publish", post(posts::post_publish_post))
+        .route(
+            "/posts/:post_id/unpublish",
+            post(posts::post_unpublish_post),
+        )
+        .route_layer(middleware::from_fn(require_posts_write_scope));
+
+    // Pages can only be managed by editors and admins, which the page handlers check.
+    // Like posts, they are covered by the `posts-write` scope.
+    let pages_routes = Router::new()
+        .route(
+            "/pages",
+            get(pages::get_pages).post(pages::post_create_page),
+        )
+        .route(
+            "/pages/:page_id",
+            get(pages::get_page)
+                .put(pages::put_page)
+                .delete(pages::delete_page),
+        )
+        .route("/pages/:page_id/publish", post(pages::post_publish_page))
+        .route(
+            "/pages/:page_id/unpublish",
+            post(pages::post_unpublish_page),
+        )
+        .route_layer(middleware::from_fn(require_posts_write_scope));
+
+    let settings_routes = Router::new()
+        .route(
+            "/settings",
+            get(settings::get_settings).put(settings::put_settings),
+        )
+        .route_layer(middleware::from_fn(require_settings_write_scope));
+
+    Router::new()
+        .merge(posts_routes)
+        .merge(pages_routes)
+        .merge(settings_routes)
+        .route_layer(middleware::from_fn(authenticate))
+}
+
+/// Error returned by API handlers, which is sent to the client as a JSON object
+/// of the form `{"error": {"status": 404, "message": "post not found"}}`.
+pub(crate) struct ApiError(ErrorResponse);
+
+impl From<ErrorResponse> for ApiError {
+    fn from(error: ErrorResponse) -> Self {
+        ApiError(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = self.0;
+
+        (
+            status,
+            Json(json!({
+                "error": {
+                    "status": status.as_u16(),
+                    "message": message,
+                },
+            })),
+        )
+            .into_response()
+    }
+}
+
+/// Like `Json`, but rejects invalid requests with an `ApiError`.
+pub(crate) struct ApiJson<T>(pub(crate) T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    Json<T>: FromRequest<S, B>,
+    S: Send + Sync,
+    B: Send + 'static,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(request, state).await {
+            Ok(Json(value)) => Ok(ApiJson(value)),
+            Err(rejection) => {
+                let status = rejection.into_response().status();
+
+                Err(ApiError((
+                    status,
+                    if status == StatusCode::UNSUPPORTED_MEDIA_TYPE {
+                        "expected request with 'Content-Type: application/json'"
+                    } else {
+                        "invalid JSON request body"
+                    },
+                )))
+            }
+        }
+    }
+}
+
+/// The scope of the API token a request was authenticated with,
+/// or `None` if it was authenticated with a session cookie,
+/// in which case the user's role is the only restriction.
+#[derive(Clone, Copy)]
+struct RequestScope(Option<ApiTokenScope>);
+
+/// Extracts the token from an `Authorization: Bearer <token>` header.
+pub(crate) fn bearer_token(authorization: &HeaderValue) -> Option<&str> {
+    authorization
+        .to_str()
+        .ok()
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Returns the user `token` belongs to, along with the token's scope,
+/// or `None` if the token is invalid. Using a token updates its last-used time.
+pub(crate) async fn token_user(
+    connection: &DatabaseConnection,
+    token: &str,
+) -> Result<Option<(user::Model, ApiTokenScope)>, ErrorResponse> {
+    let (token, user) = match ApiToken::find()
+        .filter(api_token::Column::TokenHash.eq(hash_api_token(token)))
+        .find_also_related(User)
+        .one(connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve API token",
+            )
+        })? {
+        Some((token, Some(user))) => (token, user),
+        _ => return Ok(None),
+    };
+
+    let scope = token.scope;
+
+    let mut token: api_token::ActiveModel = token.into();
+    token.last_used = Set(Some(Utc::now().naive_utc()));
+    token.update(connection).await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to update API token",
+        )
+    })?;
+
+    Ok(Some((user, scope)))
+}
+
+/// Middleware that makes the calling user available to handlers.
+/// Requests are authenticated either with an API token in the `Authorization` header,
+/// or with the session cookie of a logged-in user.
+/// Unlike its admin counterpart, it responds with an error instead of redirecting.
+async fn authenticate<B>(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    jar: CookieJar,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let result = match request.headers().get(AUTHORIZATION) {
+        Some(authorization) => token_user(
+            database_connection,
+            bearer_token(authorization).unwrap_or_default(),
+        )
+        .await
+        .and_then(|token_user| {
+            token_user
+                .map(|(user, scope)| (user, RequestScope(Some(scope))))
+                .ok_or((StatusCode::UNAUTHORIZED, "invalid API token"))
+        }),
+        None => session_user(database_connection, &jar)
+            .await
+            .and_then(|user| {
+                user.map(|user| (user, RequestScope(None)))
+                    .ok_or((StatusCode::UNAUTHORIZED, "not logged in"))
+            }),
+    };
+
+    match result {
+        Ok((user, scope)) => {
+            request.extensions_mut().insert(user);
+            request.extensions_mut().insert(scope);
+            next.run(request).await
+        }
+        Err(error) => ApiError(error).into_response(),
+    }
+}
+
+async fn require_write_scope_layer<B>(
+    scope: ApiTokenScope,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    // All scopes allow reading.
+    if request.method() == Method::GET || request.method() == Method::HEAD {
+        return next.run(request).await;
+    }
+
+    // `authenticate` must run first to make the scope available.
+    match request.extensions().get::<RequestScope>() {
+        Some(RequestScope(None)) => next.run(request).await,
+        Some(RequestScope(Some(request_scope))) if *request_scope == scope => {
+            next.run(request).await
+        }
+        _ => ApiError((
+            StatusCode::FORBIDDEN,
+            "API token does not have the required scope",
+        ))
+        .into_response(),
+    }
+}
+
+/// Middleware that restricts write access to tokens with the `posts-write` scope.
+async fn require_posts_write_scope<B>(request: Request<B>, next: Next<B>) -> Response {
+    require_write_scope_layer(ApiTokenScope::PostsWrite, request, next).await
+}
+
+/// Middleware that restricts write access to tokens with the `settings-write` scope.
+async fn require_settings_write_scope<B>(request: Request<B>, next: Next<B>) -> Response {
+    require_write_scope_layer(ApiTokenScope::SettingsWrite, request, next).await
+}
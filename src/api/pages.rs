@@ -0,0 +1,181 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Json};
+use entity::{page, sea_orm_active_enums::Role, user};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    admin::{
+        auth::require_role,
+        pages::{
+            all_pages, delete_page as delete_page_by_id, page_by_id, save_page,
+            set_page_is_published, PageInput,
+        },
+    },
+    api::{ApiError, ApiJson},
+};
+
+#[derive(Serialize)]
+pub(super) struct ApiPage {
+    id: i32,
+    title: String,
+    url: String,
+    menu_order: Option<i32>,
+    /// In UTC, formatted as `YYYY-MM-DDTHH:MM:SS`. Records when the page was last saved.
+    time: String,
+    is_published: bool,
+    content_markdown: String,
+    content_html: String,
+    author_id: Option<i32>,
+}
+
+impl From<page::Model> for ApiPage {
+    fn from(page: page::Model) -> Self {
+        ApiPage {
+            id: page.id,
+            title: page.title,
+            url: page.url,
+            menu_order: page.menu_order,
+            time: page.time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            is_published: page.is_published,
+            content_markdown: page.content_markdown,
+            content_html: page.content_html,
+            author_id: page.author_id,
+        }
+    }
+}
+
+fn parse_page_id(page_id: &str) -> Result<i32, ApiError> {
+    Ok(page_id
+        .parse()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "invalid page ID"))?)
+}
+
+pub(super) async fn get_pages(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_role(user, Role::Editor)?;
+
+    Ok(Json(
+        all_pages(database_connection)
+            .await?
+            .into_iter()
+            .map(ApiPage::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+pub(super) async fn get_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(page_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_role(user, Role::Editor)?;
+
+    let page = page_by_id(database_connection, parse_page_id(&page_id)?).await?;
+
+    Ok(Json(ApiPage::from(page)))
+}
+
+/// Same as the fields of the page editor, except that the menu order is a number.
+#[derive(Debug, Deserialize)]
+pub(super) struct ApiPageInput {
+    title: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    menu_order: Option<i32>,
+    #[serde(default)]
+    content: String,
+}
+
+impl From<ApiPageInput> for PageInput {
+    fn from(page_input: ApiPageInput) -> Self {
+        PageInput {
+            title: page_input.title,
+            url: page_input.url,
+            menu_order: page_input
+                .menu_order
+                .map(|menu_order| menu_order.to_string())
+                .unwrap_or_default(),
+            content: page_input.content,
+        }
+    }
+}
+
+pub(super) async fn post_create_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    ApiJson(page_input): ApiJson<ApiPageInput>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_role(user, Role::Editor)?;
+
+    let page = save_page(database_connection, user, None, &page_input.into(), None).await?;
+
+    Ok((StatusCode::CREATED, Json(ApiPage::from(page))))
+}
+
+pub(super) async fn put_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(page_id): Path<String>,
+    ApiJson(page_input): ApiJson<ApiPageInput>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_role(user, Role::Editor)?;
+
+    let page = save_page(
+        database_connection,
+        user,
+        Some(parse_page_id(&page_id)?),
+        &page_input.into(),
+        None,
+    )
+    .await?;
+
+    Ok(Json(ApiPage::from(page)))
+}
+
+async fn set_is_published(
+    database_connection: &DatabaseConnection,
+    user: &user::Model,
+    page_id: &str,
+    is_published: bool,
+) -> Result<impl IntoResponse, ApiError> {
+    require_role(user, Role::Editor)?;
+
+    let page =
+        set_page_is_published(database_connection, parse_page_id(page_id)?, is_published).await?;
+
+    Ok(Json(ApiPage::from(page)))
+}
+
+pub(super) async fn post_publish_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(page_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    set_is_published(database_connection, user, &page_id, true).await
+}
+
+pub(super) async fn post_unpublish_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(page_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    set_is_published(database_connection, user, &page_id, false).await
+}
+
+pub(super) async fn delete_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(page_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_role(user, Role::Editor)?;
+
+    delete_page_by_id(database_connection, parse_page_id(&page_id)?).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,297 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Json};
+use entity::{page, prelude::Tag, tag, user};
+use sea_orm::{DatabaseConnection, ModelTrait, QueryOrder};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    admin::posts::{
+        delete_post as delete_post_by_id, editable_post_by_id, editable_posts, save_post,
+        set_post_is_published, PostInput,
+    },
+    announce::announce_post,
+    api::{ApiError, ApiJson},
+};
+
+#[derive(Serialize)]
+pub(super) struct ApiPost {
+    id: i32,
+    title: String,
+    url: String,
+    /// In UTC, formatted as `YYYY-MM-DDTHH:MM:SS`.
+    time: String,
+    is_published: bool,
+    tags: Vec<String>,
+    content_markdown: String,
+    content_html: String,
+    author_id: Option<i32>,
+}
+
+impl ApiPost {
+    fn new(post: page::Model, tags: Vec<tag::Model>) -> Self {
+        let mut tags: Vec<String> = tags.into_iter().map(|tag| tag.name).collect();
+        tags.sort();
+
+        ApiPost {
+            id: post.id,
+            title: post.title,
+            url: post.url,
+            time: post.time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            is_published: post.is_published,
+            tags,
+            content_markdown: post.content_markdown,
+            content_html: post.content_html,
+            author_id: post.author_id,
+        }
+    }
+}
+
+fn parse_post_id(post_id: &str) -> Result<i32, ApiError> {
+    Ok(post_id
+        .parse()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "invalid post ID"))?)
+}
+
+async fn api_post(connection: &DatabaseConnection, post: page::Model) -> Result<ApiPost, ApiError> {
+    let tags = post
+        .find_related(Tag)
+        .all(connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve tags"))?;
+
+    Ok(ApiPost::new(post, tags))
+}
+
+pub(super) async fn get_posts(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+) -> Result<impl IntoResponse, ApiError> {
+    let posts = editable_posts(user)
+        .order_by_desc(page::Column::Time)
+        .find_with_related(Tag)
+        .all(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve posts",
+            )
+        })?;
+
+    Ok(Json(
+        posts
+            .into_iter()
+            .map(|(post, tags)| ApiPost::new(post, tags))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+pub(super) async fn get_post(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(post_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let post = editable_post_by_id(database_connection, user, parse_post_id(&post_id)?).await?;
+
+    Ok(Json(api_post(database_connection, post).await?))
+}
+
+/// Same as the fields of the post editor, except that tags are given as a list.
+/// An empty date means the current time for new posts,
+/// and the existing time for updated posts. Likewise, an empty URL is derived
+/// from the title for new posts, and left unchanged for updated posts,
+/// so that changing the title doesn't break existing links.
+#[derive(Debug, Deserialize)]
+pub(super) struct ApiPostInput {
+    title: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    date: String,
+    #[serde(default)]
+    time: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    content: String,
+}
+
+impl ApiPostInput {
+    fn into_post_input(self, existing_post: Option<&page::Model>) -> PostInput {
+        let (date, time) = match existing_post {
+            Some(post) if self.date.is_empty() => (
+                post.time.format("%Y-%m-%d").to_string(),
+                post.time.format("%H:%M:%S").to_string(),
+            ),
+            _ => (self.date, self.time),
+        };
+
+        let url = match existing_post {
+            Some(post) if self.url.is_empty() => post.url.clone(),
+            _ => self.url,
+        };
+
+        PostInput {
+            title: self.title,
+            url,
+            date,
+            time,
+            // Tag names are sanitized by the post editor's tag parser.
+            tags: self.tags.join(","),
+            content: self.content,
+        }
+    }
+}
+
+pub(super) async fn post_create_post(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    ApiJson(post_input): ApiJson<ApiPostInput>,
+) -> Result<impl IntoResponse, ApiError> {
+    let post = save_post(
+        database_connection,
+        user,
+        None,
+        &post_input.into_post_input(None),
+        None,
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(api_post(database_connection, post).await?),
+    ))
+}
+
+pub(super) async fn put_post(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(post_id): Path<String>,
+    ApiJson(post_input): ApiJson<ApiPostInput>,
+) -> Result<impl IntoResponse, ApiError> {
+    let post = editable_post_by_id(database_connection, user, parse_post_id(&post_id)?).await?;
+
+    let post = save_post(
+        database_connection,
+        user,
+        Some(post.id),
+        &post_input.into_post_input(Some(&post)),
+        None,
+    )
+    .await?;
+
+    Ok(Json(api_post(database_connection, post).await?))
+}
+
+async fn set_is_published(
+    database_connection: &DatabaseConnection,
+    user: &user::Model,
+    post_id: &str,
+    is_published: bool,
+) -> Result<impl IntoResponse, ApiError> {
+    let post = set_post_is_published(
+        database_connection,
+        user,
+        parse_post_id(post_id)?,
+        is_published,
+    )
+    .await?;
+
+    if is_published {
+        announce_post(database_connection, &post).await?;
+    }
+
+    Ok(Json(api_post(database_connection, post).await?))
+}
+
+pub(super) async fn post_publish_post(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(post_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    set_is_published(database_connection, user, &post_id, true).await
+}
+
+pub(super) async fn post_unpublish_post(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(post_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    set_is_published(database_connection, user, &post_id, false).await
+}
+
+pub(super) async fn delete_post(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    Path(post_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    delete_post_by_id(database_connection, user, parse_post_id(&post_id)?).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn api_post_input(url: &str, date: &str) -> ApiPostInput {
+        ApiPostInput {
+            title: "New title".to_owned(),
+            url: url.to_owned(),
+            date: date.to_owned(),
+            time: String::new(),
+            tags: vec!["one".to_owned(), "two".to_owned()],
+            content: String::new(),
+        }
+    }
+
+    fn existing_post() -> page::Model {
+        page::Model {
+            id: 1,
+            time: NaiveDate::from_ymd_opt(2023, 1, 15)
+                .unwrap()
+                .and_hms_opt(10, 30, 0)
+                .unwrap(),
+            title: "Old title".to_owned(),
+            url: "old-title".to_owned(),
+            content_markdown: String::new(),
+            content_html: String::new(),
+            is_post: true,
+            is_published: true,
+            menu_order: None,
+            author_id: None,
+            announced: None,
+            modified: None,
+        }
+    }
+
+    #[test]
+    fn update_keeps_url_and_time_unless_given() {
+        let post_input = api_post_input("", "").into_post_input(Some(&existing_post()));
+
+        assert_eq!(post_input.title, "New title");
+        assert_eq!(post_input.url, "old-title");
+        assert_eq!(post_input.date, "2023-01-15");
+        assert_eq!(post_input.time, "10:30:00");
+        assert_eq!(post_input.tags, "one,two");
+
+        let post_input =
+            api_post_input("new-url", "2023-02-01").into_post_input(Some(&existing_post()));
+
+        assert_eq!(post_input.url, "new-url");
+        assert_eq!(post_input.date, "2023-02-01");
+        assert_eq!(post_input.time, "");
+    }
+
+    #[test]
+    fn create_leaves_url_and_time_to_defaults() {
+        let post_input = api_post_input("", "").into_post_input(None);
+
+        assert_eq!(post_input.url, "");
+        assert_eq!(post_input.date, "");
+    }
+}
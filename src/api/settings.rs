@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use axum::{http::StatusCode, response::IntoResponse, Extension, Json};
+use entity::{sea_orm_active_enums::Role, user};
+use sea_orm::{ActiveModelTrait, DatabaseConnection, Set};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    admin::{
+        auth::require_role,
+        settings::{save_settings, SettingsInput},
+    },
+    api::{ApiError, ApiJson},
+    markdown::markdown_to_html,
+    settings,
+};
+
+/// The settings from the admin interface, including the header, footer, CSS and JavaScript.
+/// The HTML of the header and footer is rendered from their Markdown.
+#[derive(Serialize, Deserialize)]
+pub(super) struct ApiSettings {
+    site_title: String,
+    site_url: String,
+    posts_per_page: i32,
+    robots_txt: String,
+    header_markdown: String,
+    footer_markdown: String,
+    css: String,
+    javascript: String,
+    spam_min_fill_seconds: i32,
+    spam_max_submissions_per_hour: i32,
+    spam_score_threshold: i32,
+}
+
+impl From<settings::Model> for ApiSettings {
+    fn from(settings: settings::Model) -> Self {
+        ApiSettings {
+            site_title: settings.site_title,
+            site_url: settings.site_url,
+            posts_per_page: settings.posts_per_page,
+            robots_txt: settings.robots_txt,
+            header_markdown: settings.header_markdown,
+            footer_markdown: settings.footer_markdown,
+            css: settings.css,
+            javascript: settings.javascript,
+            spam_min_fill_seconds: settings.spam_min_fill_seconds,
+            spam_max_submissions_per_hour: settings.spam_max_submissions_per_hour,
+            spam_score_threshold: settings.spam_score_threshold,
+        }
+    }
+}
+
+pub(super) async fn get_settings(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_role(user, Role::Admin)?;
+
+    Ok(Json(ApiSettings::from(
+        settings(database_connection).await?,
+    )))
+}
+
+pub(super) async fn put_settings(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref user): Extension<user::Model>,
+    ApiJson(settings_input): ApiJson<ApiSettings>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_role(user, Role::Admin)?;
+
+    // Validates the input, so the remaining settings are only saved if it is valid.
+    let mut settings: settings::ActiveModel = save_settings(
+        database_connection,
+        &SettingsInput {
+            site_title: settings_input.site_title,
+            site_url: settings_input.site_url,
+            posts_per_page: settings_input.posts_per_page.to_string(),
+            robots_txt: settings_input.robots_txt,
+            spam_min_fill_seconds: settings_input.spam_min_fill_seconds.to_string(),
+            spam_max_submissions_per_hour: settings_input.spam_max_submissions_per_hour.to_string(),
+            spam_score_threshold: settings_input.spam_score_threshold.to_string(),
+        },
+    )
+    .await?
+    .into();
+
+    settings.header_html = Set(markdown_to_html(&settings_input.header_markdown));
+    settings.header_markdown = Set(settings_input.header_markdown);
+    settings.footer_html = Set(markdown_to_html(&settings_input.footer_markdown));
+    settings.footer_markdown = Set(settings_input.footer_markdown);
+    settings.css = Set(settings_input.css);
+    settings.javascript = Set(settings_input.javascript);
+
+    let settings = settings
+        .update(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save settings"))?;
+
+    Ok(Json(ApiSettings::from(settings)))
+}
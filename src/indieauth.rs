@@ -0,0 +1,982 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+//! IndieAuth provider (https://indieauth.spec.indieweb.org/), which allows
+//! admins to sign in to other sites using the site's URL as their identity,
+//! and issues API tokens to clients such as Micropub editors.
+
+use askama::Template;
+use axum::{
+    extract::{Host, OriginalUri, Query},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Extension, Form, Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{Duration, Utc};
+use entity::{
+    api_token, authorization_code,
+    prelude::AuthorizationCode,
+    sea_orm_active_enums::{ApiTokenScope, Role},
+    user,
+};
+use reqwest::{Client, Url};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter, Set,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    admin::{
+        api_tokens::hash_api_token,
+        auth::{require_role, session_user},
+        is_valid_site_url,
+    },
+    random_token, settings,
+    site::base_url,
+    webmention::{header_links, html_links, http_client, response_text, send},
+    ErrorResponse, HtmlTemplate, ADMIN_URL_PREFIX,
+};
+
+const AUTHORIZATION_CODE_LIFETIME_MINUTES: i64 = 10;
+
+/// The scopes that allow changing posts. API tokens can allow either
+/// all of them, or only `create`.
+const POST_SCOPES: [&str; 3] = ["create", "update", "delete"];
+
+pub(super) fn router() -> Router {
+    Router::new()
+        .route("/.well-known/oauth-authorization-server", get(get_metadata))
+        .route(
+            "/indieauth/auth",
+            get(get_authorization).post(post_redeem_profile),
+        )
+        .route("/indieauth/auth/approve", post(post_approve_authorization))
+        .route("/indieauth/token", post(post_redeem_token))
+}
+
+/// The identity URL of the site, which is also the IndieAuth issuer.
+async fn me(connection: &DatabaseConnection, host: &str) -> Result<String, ErrorResponse> {
+    Ok(format!("{}/", base_url(&settings(connection).await?, host)))
+}
+
+async fn get_metadata(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Host(host): Host,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let me = me(database_connection, &host).await?;
+
+    Ok(Json(json!({
+        "issuer": me,
+        "authorization_endpoint": format!("{}indieauth/auth", me),
+        "token_endpoint": format!("{}indieauth/token", me),
+        "code_challenge_methods_supported": ["S256"],
+        "grant_types_supported": ["authorization_code"],
+        "response_types_supported": ["code"],
+        "scopes_supported": ["create", "update", "delete"],
+        "authorization_response_iss_parameter_supported": true,
+    })))
+}
+
+/// Returns the API token scope that grants as much of the space-separated IndieAuth
+/// `scope` as possible without granting more, or `None` if it requests post scopes,
+/// but none of them can be granted. Unsupported scopes are ignored.
+fn token_scope(scope: &str) -> Option<ApiTokenScope> {
+    let scopes: Vec<&str> = scope.split_whitespace().collect();
+
+    if POST_SCOPES
+        .iter()
+        .all(|post_scope| scopes.contains(post_scope))
+    {
+        Some(ApiTokenScope::PostsWrite)
+    } else if scopes.contains(&"create") {
+        Some(ApiTokenScope::PostsCreate)
+    } else if POST_SCOPES
+        .iter()
+        .any(|post_scope| scopes.contains(post_scope))
+    {
+        None
+    } else {
+        Some(ApiTokenScope::Read)
+    }
+}
+
+/// Returns the scopes from the space-separated `scope` that are actually granted,
+/// i.e. without the post scopes that `token_scope` can't grant.
+fn granted_scopes(scope: &str) -> Vec<&str> {
+    let granted_post_scopes: &[&str] = match token_scope(scope) {
+        Some(ApiTokenScope::PostsWrite) => &POST_SCOPES,
+        Some(ApiTokenScope::PostsCreate) => &["create"],
+        _ => &[],
+    };
+
+    scope
+        .split_whitespace()
+        .filter(|scope| !POST_SCOPES.contains(scope) || granted_post_scopes.contains(scope))
+        .collect()
+}
+
+/// Returns whether both URLs have the same scheme, host and port.
+fn is_same_origin(url: &str, other_url: &str) -> bool {
+    match (Url::parse(url), Url::parse(other_url)) {
+        (Ok(url), Ok(other_url)) => url.origin() == other_url.origin(),
+        _ => false,
+    }
+}
+
+/// Returns the redirect URIs published by the client with the ID `client_id`,
+/// either in its metadata document, or as `redirect_uri` links.
+async fn published_redirect_uris(client: &Client, client_id: &str) -> Option<Vec<Url>> {
+    let response = send(client.get(client_id)).await?.error_for_status().ok()?;
+
+    let mut redirect_uris = header_links(&response, "redirect_uri");
+
+    // Relative URLs are resolved against the URL after redirects.
+    let url = response.url().clone();
+
+    let text = response_text(response).await.ok()?;
+
+    // Clients following the current specification publish a JSON document,
+    // older ones an HTML page.
+    if let Ok(metadata) = serde_json::from_str::<Value>(&text) {
+        redirect_uris.extend(
+            metadata["redirect_uris"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .filter_map(|redirect_uri| url.join(redirect_uri).ok()),
+        );
+    } else {
+        redirect_uris.extend(html_links(&text, &url, "redirect_uri"));
+    }
+
+    Some(redirect_uris)
+}
+
+/// Appends query parameters to `url`, which may already have some.
+fn with_parameters(url: &str, parameters: &[(&str, &str)]) -> String {
+    format!(
+        "{}{}{}",
+        url,
+        if url.contains('?') { '&' } else { '?' },
+        serde_urlencoded::to_string(parameters).unwrap(),
+    )
+}
+
+#[derive(Deserialize)]
+struct AuthorizationRequest {
+    response_type: String,
+    client_id: String,
+    redirect_uri: String,
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    code_challenge: String,
+    #[serde(default)]
+    code_challenge_method: String,
+    #[serde(default)]
+    scope: String,
+}
+
+impl AuthorizationRequest {
+    fn validate(&self) -> Result<(), ErrorResponse> {
+        if self.response_type != "code" {
+            Err((
+                StatusCode::BAD_REQUEST,
+                "unsupported response type, must be 'code'",
+            ))
+        } else if !is_valid_site_url(&self.client_id) || !is_valid_site_url(&self.redirect_uri) {
+            Err((
+                StatusCode::BAD_REQUEST,
+                "invalid client ID or redirect URI, must be HTTP(S) URLs",
+            ))
+        } else if self.code_challenge.is_empty() || self.code_challenge_method != "S256" {
+            Err((
+                StatusCode::BAD_REQUEST,
+                "missing PKCE code challenge, must use method 'S256'",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks that the redirect URI belongs to the client. Otherwise, anyone could
+    /// pose as the client and have codes sent to them. Redirect URIs on another origin
+    /// than the client ID must be published by the client.
+    async fn verify_redirect_uri(&self, client: &Client) -> Result<(), ErrorResponse> {
+        if is_same_origin(&self.redirect_uri, &self.client_id) {
+            return Ok(());
+        }
+
+        let error = (
+            StatusCode::BAD_REQUEST,
+            "redirect URI is neither on the client's host nor published by the client",
+        );
+
+        let redirect_uri = Url::parse(&self.redirect_uri).map_err(|_| error)?;
+
+        if published_redirect_uris(client, &self.client_id)
+            .await
+            .unwrap_or_default()
+            .contains(&redirect_uri)
+        {
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+}
+
+fn client() -> Result<Client, ErrorResponse> {
+    http_client().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to create HTTP client",
+        )
+    })
+}
+
+/// Returns the user who is logged in to the admin interface, if they are an admin.
+/// Only admins may sign in as the site, because the site's identity is the owner's identity.
+async fn admin_user(
+    connection: &DatabaseConnection,
+    jar: &CookieJar,
+) -> Result<Option<user::Model>, ErrorResponse> {
+    match session_user(connection, jar).await? {
+        Some(user) => {
+            require_role(&user, Role::Admin)?;
+            Ok(Some(user))
+        }
+        None => Ok(None),
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin/indieauth.html")]
+struct AuthorizationTemplate<'a> {
+    admin_url_prefix: &'a str,
+    title: &'a str,
+    current_role: Role,
+    me: String,
+    client_id: String,
+    redirect_uri: String,
+    scopes: Vec<String>,
+    query: String,
+}
+
+async fn get_authorization(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    jar: CookieJar,
+    Host(host): Host,
+    OriginalUri(uri): OriginalUri,
+    Query(request): Query<AuthorizationRequest>,
+) -> Result<Response, ErrorResponse> {
+    let user = match admin_user(database_connection, &jar).await? {
+        Some(user) => user,
+        None => {
+            return Ok(Redirect::to(&format!(
+                "{}/login?{}",
+                ADMIN_URL_PREFIX,
+                serde_urlencoded::to_string([("next", uri.to_string())]).unwrap(),
+            ))
+            .into_response());
+        }
+    };
+
+    request.validate()?;
+    request.verify_redirect_uri(&client()?).await?;
+
+    Ok(HtmlTemplate(AuthorizationTemplate {
+        admin_url_prefix: ADMIN_URL_PREFIX,
+        current_role: user.role,
+        title: "Sign in",
+        me: me(database_connection, &host).await?,
+        // Only what is actually granted is shown, so the user isn't misled.
+        scopes: granted_scopes(&request.scope)
+            .into_iter()
+            .map(str::to_owned)
+            .collect(),
+        client_id: request.client_id,
+        redirect_uri: request.redirect_uri,
+        query: uri.query().unwrap_or_default().to_owned(),
+    })
+    .into_response())
+}
+
+#[derive(Deserialize)]
+struct ApprovalInput {
+    decision: String,
+}
+
+async fn post_approve_authorization(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    jar: CookieJar,
+    Host(host): Host,
+    Query(request): Query<AuthorizationRequest>,
+    Form(approval_input): Form<ApprovalInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let user = admin_user(database_connection, &jar)
+        .await?
+        .ok_or((StatusCode::UNAUTHORIZED, "not logged in"))?;
+
+    request.validate()?;
+    request.verify_redirect_uri(&client()?).await?;
+
+    let issuer = me(database_connection, &host).await?;
+
+    if approval_input.decision != "approve" {
+        return Ok(Redirect::to(&with_parameters(
+            &request.redirect_uri,
+            &[
+                ("error", "access_denied"),
+                ("state", &request.state),
+                ("iss", &issuer),
+            ],
+        )));
+    }
+
+    let now = Utc::now().naive_utc();
+
+    // Expired codes are useless, so this is a good opportunity to clean them up.
+    AuthorizationCode::delete_many()
+        .filter(authorization_code::Column::Expires.lte(now))
+        .exec(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to delete expired authorization codes",
+            )
+        })?;
+
+    let code = random_token();
+
+    authorization_code::ActiveModel {
+        user_id: Set(user.id),
+        code_hash: Set(hash_api_token(&code)),
+        client_id: Set(request.client_id),
+        redirect_uri: Set(request.redirect_uri.clone()),
+        code_challenge: Set(request.code_challenge),
+        scope: Set(request.scope),
+        expires: Set(now + Duration::minutes(AUTHORIZATION_CODE_LIFETIME_MINUTES)),
+        ..Default::default()
+    }
+    .insert(database_connection)
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to create authorization code",
+        )
+    })?;
+
+    Ok(Redirect::to(&with_parameters(
+        &request.redirect_uri,
+        &[("code", &code), ("state", &request.state), ("iss", &issuer)],
+    )))
+}
+
+/// Error in the format defined by OAuth 2.0, which IndieAuth is based on.
+struct OAuthError {
+    status: StatusCode,
+    error: &'static str,
+    description: &'static str,
+}
+
+impl From<ErrorResponse> for OAuthError {
+    fn from((status, description): ErrorResponse) -> Self {
+        OAuthError {
+            status,
+            error: if status.is_server_error() {
+                "server_error"
+            } else {
+                "invalid_request"
+            },
+            description,
+        }
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(json!({
+                "error": self.error,
+                "error_description": self.description,
+            })),
+        )
+            .into_response()
+    }
+}
+
+fn invalid_grant(description: &'static str) -> OAuthError {
+    OAuthError {
+        status: StatusCode::BAD_REQUEST,
+        error: "invalid_grant",
+        description,
+    }
+}
+
+#[derive(Deserialize)]
+struct RedemptionInput {
+    grant_type: String,
+    code: String,
+    client_id: String,
+    redirect_uri: String,
+    code_verifier: String,
+}
+
+/// Exchanges an authorization code for the data it was issued with.
+/// Codes can only be used once.
+async fn redeem_code(
+    connection: &DatabaseConnection,
+    redemption_input: &RedemptionInput,
+) -> Result<authorization_code::Model, OAuthError> {
+    if redemption_input.grant_type != "authorization_code" {
+        return Err(OAuthError {
+            status: StatusCode::BAD_REQUEST,
+            error: "unsupported_grant_type",
+            description: "unsupported grant type, must be 'authorization_code'",
+        });
+    }
+
+    let code = AuthorizationCode::find()
+        .filter(authorization_code::Column::CodeHash.eq(hash_api_token(&redemption_input.code)))
+        .one(connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve authorization code",
+            )
+        })?
+        .ok_or_else(|| invalid_grant("invalid authorization code"))?;
+
+    code.clone().delete(connection).await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to delete authorization code",
+        )
+    })?;
+
+    let code_challenge = base64::encode_config(
+        Sha256::digest(redemption_input.code_verifier.as_bytes()),
+        base64::URL_SAFE_NO_PAD,
+    );
+
+    if code.expires <= Utc::now().naive_utc() {
+        Err(invalid_grant("authorization code has expired"))
+    } else if code.client_id != redemption_input.client_id
+        || code.redirect_uri != redemption_input.redirect_uri
+    {
+        Err(invalid_grant(
+            "client ID or redirect URI do not match the authorization request",
+        ))
+    } else if code.code_challenge != code_challenge {
+        Err(invalid_grant("invalid PKCE code verifier"))
+    } else {
+        Ok(code)
+    }
+}
+
+/// Redeems an authorization code for the user's identity only.
+async fn post_redeem_profile(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Host(host): Host,
+    Form(ref redemption_input): Form<RedemptionInput>,
+) -> Result<impl IntoResponse, OAuthError> {
+    redeem_code(database_connection, redemption_input).await?;
+
+    Ok(Json(json!({
+        "me": me(database_connection, &host).await?,
+    })))
+}
+
+/// Redeems an authorization code for an API token.
+async fn post_redeem_token(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Host(host): Host,
+    Form(ref redemption_input): Form<RedemptionInput>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let code = redeem_code(database_connection, redemption_input).await?;
+
+    if code.scope.trim().is_empty() {
+        return Err(invalid_grant(
+            "authorization code was issued without scope, and cannot be used to obtain a token",
+        ));
+    }
+
+    let scope = token_scope(&code.scope).ok_or(OAuthError {
+        status: StatusCode::BAD_REQUEST,
+        error: "invalid_scope",
+        description: "post scopes must include 'create', or be 'create update delete'",
+    })?;
+
+    let token = random_token();
+
+    api_token::ActiveModel {
+        user_id: Set(code.user_id),
+        name: Set(format!("{} (IndieAuth)", code.client_id)),
+        token_hash: Set(hash_api_token(&token)),
+        scope: Set(scope),
+        created: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(database_connection)
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to create API token",
+        )
+    })?;
+
+    Ok(Json(json!({
+        "access_token": token,
+        "token_type": "Bearer",
+        // Clients must be told if they got less than they requested.
+        "scope": granted_scopes(&code.scope).join(" "),
+        "me": me(database_connection, &host).await?,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{
+            header::{HOST, LINK, LOCATION},
+            Request,
+        },
+        response::Html,
+        Server,
+    };
+    use entity::prelude::ApiToken;
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::Database;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const CLIENT_ID: &str = "https://app.example/";
+    const REDIRECT_URI: &str = "https://app.example/callback";
+    const CODE_VERIFIER: &str = "a-code-verifier-that-is-long-enough-for-pkce";
+
+    async fn database_with_admin() -> (DatabaseConnection, user::Model) {
+        let connection = Database::connect("sqlite::memory:").await.unwrap();
+
+        Migrator::up(&connection, None).await.unwrap();
+
+        let user = user::ActiveModel {
+            name: Set("jane".to_owned()),
+            password_hash: Set(String::new()),
+            role: Set(Role::Admin),
+            ..Default::default()
+        }
+        .insert(&connection)
+        .await
+        .unwrap();
+
+        (connection, user)
+    }
+
+    /// Stores an authorization code for `CLIENT_ID` and `REDIRECT_URI`,
+    /// whose challenge matches `CODE_VERIFIER`.
+    async fn insert_code(
+        connection: &DatabaseConnection,
+        user: &user::Model,
+        code: &str,
+        scope: &str,
+        expires_in: Duration,
+    ) {
+        authorization_code::ActiveModel {
+            user_id: Set(user.id),
+            code_hash: Set(hash_api_token(code)),
+            client_id: Set(CLIENT_ID.to_owned()),
+            redirect_uri: Set(REDIRECT_URI.to_owned()),
+            code_challenge: Set(base64::encode_config(
+                Sha256::digest(CODE_VERIFIER.as_bytes()),
+                base64::URL_SAFE_NO_PAD,
+            )),
+            scope: Set(scope.to_owned()),
+            expires: Set(Utc::now().naive_utc() + expires_in),
+            ..Default::default()
+        }
+        .insert(connection)
+        .await
+        .unwrap();
+    }
+
+    fn redemption_input(code: &str) -> RedemptionInput {
+        RedemptionInput {
+            grant_type: "authorization_code".to_owned(),
+            code: code.to_owned(),
+            client_id: CLIENT_ID.to_owned(),
+            redirect_uri: REDIRECT_URI.to_owned(),
+            code_verifier: CODE_VERIFIER.to_owned(),
+        }
+    }
+
+    async fn redeem_error(
+        connection: &DatabaseConnection,
+        redemption_input: &RedemptionInput,
+    ) -> (&'static str, &'static str) {
+        let error = redeem_code(connection, redemption_input)
+            .await
+            .err()
+            .unwrap();
+
+        (error.error, error.description)
+    }
+
+    #[tokio::test]
+    async fn redeem_code_only_once() {
+        let (connection, user) = database_with_admin().await;
+        insert_code(&connection, &user, "code", "", Duration::minutes(10)).await;
+
+        let code = redeem_code(&connection, &redemption_input("code"))
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(code.user_id, user.id);
+
+        assert_eq!(
+            redeem_error(&connection, &redemption_input("code")).await,
+            ("invalid_grant", "invalid authorization code"),
+        );
+    }
+
+    #[tokio::test]
+    async fn redeem_code_rejects_wrong_code_verifier() {
+        let (connection, user) = database_with_admin().await;
+        insert_code(&connection, &user, "code", "", Duration::minutes(10)).await;
+
+        let mut wrong_verifier = redemption_input("code");
+        wrong_verifier.code_verifier = "another-code-verifier".to_owned();
+
+        assert_eq!(
+            redeem_error(&connection, &wrong_verifier).await,
+            ("invalid_grant", "invalid PKCE code verifier"),
+        );
+
+        // Failed attempts use up the code, so the verifier can't be guessed.
+        assert_eq!(
+            redeem_error(&connection, &redemption_input("code")).await,
+            ("invalid_grant", "invalid authorization code"),
+        );
+    }
+
+    #[tokio::test]
+    async fn redeem_code_rejects_expired_code() {
+        let (connection, user) = database_with_admin().await;
+        insert_code(&connection, &user, "code", "", Duration::minutes(-1)).await;
+
+        assert_eq!(
+            redeem_error(&connection, &redemption_input("code")).await,
+            ("invalid_grant", "authorization code has expired"),
+        );
+    }
+
+    #[tokio::test]
+    async fn redeem_code_rejects_other_client_or_redirect_uri() {
+        let (connection, user) = database_with_admin().await;
+
+        let mut other_client = redemption_input("code-1");
+        other_client.client_id = "https://other.example/".to_owned();
+
+        let mut other_redirect_uri = redemption_input("code-2");
+        other_redirect_uri.redirect_uri = "https://app.example/other".to_owned();
+
+        for redemption_input in [other_client, other_redirect_uri] {
+            insert_code(
+                &connection,
+                &user,
+                &redemption_input.code,
+                "",
+                Duration::minutes(10),
+            )
+            .await;
+
+            assert_eq!(
+                redeem_error(&connection, &redemption_input).await,
+                (
+                    "invalid_grant",
+                    "client ID or redirect URI do not match the authorization request",
+                ),
+            );
+        }
+    }
+
+    async fn redeem_token(
+        connection: &DatabaseConnection,
+        code: &str,
+    ) -> Result<Response, OAuthError> {
+        post_redeem_token(
+            Extension(connection.clone()),
+            Host("blog.example.com".to_owned()),
+            Form(redemption_input(code)),
+        )
+        .await
+        .map(IntoResponse::into_response)
+    }
+
+    #[tokio::test]
+    async fn redeem_token_issues_scope_that_was_granted() {
+        let (connection, user) = database_with_admin().await;
+        insert_code(
+            &connection,
+            &user,
+            "write",
+            "create update delete",
+            Duration::minutes(10),
+        )
+        .await;
+        insert_code(
+            &connection,
+            &user,
+            "create",
+            "create update media",
+            Duration::minutes(10),
+        )
+        .await;
+        insert_code(&connection, &user, "read", "profile", Duration::minutes(10)).await;
+
+        assert!(redeem_token(&connection, "write").await.is_ok());
+
+        let response = redeem_token(&connection, "create").await.ok().unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["scope"], "create media");
+
+        assert!(redeem_token(&connection, "read").await.is_ok());
+
+        let scopes: Vec<ApiTokenScope> = ApiToken::find()
+            .all(&connection)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|api_token| api_token.scope)
+            .collect();
+
+        assert_eq!(
+            scopes,
+            [
+                ApiTokenScope::PostsWrite,
+                ApiTokenScope::PostsCreate,
+                ApiTokenScope::Read,
+            ],
+        );
+    }
+
+    #[tokio::test]
+    async fn redeem_token_rejects_unrepresentable_scope() {
+        let (connection, user) = database_with_admin().await;
+        insert_code(&connection, &user, "empty", "", Duration::minutes(10)).await;
+        insert_code(
+            &connection,
+            &user,
+            "update",
+            "update delete",
+            Duration::minutes(10),
+        )
+        .await;
+
+        let error = redeem_token(&connection, "empty").await.err().unwrap();
+        assert_eq!(error.error, "invalid_grant");
+
+        let error = redeem_token(&connection, "update").await.err().unwrap();
+        assert_eq!(error.error, "invalid_scope");
+
+        assert!(ApiToken::find().all(&connection).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn token_scope_grants_no_more_than_requested() {
+        assert_eq!(token_scope(""), Some(ApiTokenScope::Read));
+        assert_eq!(token_scope("profile email"), Some(ApiTokenScope::Read));
+        assert_eq!(
+            token_scope("delete profile update create"),
+            Some(ApiTokenScope::PostsWrite),
+        );
+        assert_eq!(token_scope("create"), Some(ApiTokenScope::PostsCreate));
+        assert_eq!(
+            token_scope("create update media"),
+            Some(ApiTokenScope::PostsCreate),
+        );
+        assert_eq!(token_scope("update"), None);
+        assert_eq!(token_scope("profile update delete"), None);
+    }
+
+    #[test]
+    fn granted_scopes_omit_post_scopes_that_are_not_granted() {
+        assert_eq!(granted_scopes("profile"), ["profile"]);
+        assert_eq!(
+            granted_scopes("create update delete"),
+            ["create", "update", "delete"],
+        );
+        assert_eq!(
+            granted_scopes("profile create delete media"),
+            ["profile", "create", "media"],
+        );
+        assert_eq!(granted_scopes("profile update"), ["profile"]);
+    }
+
+    #[tokio::test]
+    async fn unauthenticated_authorization_redirects_to_login() {
+        let (connection, _) = database_with_admin().await;
+
+        let uri = format!(
+            "/indieauth/auth?{}",
+            serde_urlencoded::to_string([
+                ("response_type", "code"),
+                ("client_id", CLIENT_ID),
+                ("redirect_uri", REDIRECT_URI),
+            ])
+            .unwrap(),
+        );
+
+        let response = router()
+            .layer(Extension(connection))
+            .oneshot(
+                Request::get(&uri)
+                    .header(HOST, "blog.example.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(
+            response.headers()[LOCATION],
+            format!(
+                "/-/login?{}",
+                serde_urlencoded::to_string([("next", &uri)]).unwrap(),
+            ),
+        );
+    }
+
+    /// Starts a stand-in for a client on a local port, which publishes
+    /// `https://callback.example/` as a redirect URI in every supported way.
+    async fn start_client() -> String {
+        let redirect_uri = "https://callback.example/";
+
+        let router = Router::new()
+            .route(
+                "/json",
+                get(move || async move {
+                    Json(json!({
+                        "client_id": "/json",
+                        "redirect_uris": [redirect_uri],
+                    }))
+                }),
+            )
+            .route(
+                "/html",
+                get(move || async move {
+                    Html(format!(
+                        r#"<html><head><link rel="redirect_uri" href="{}"></head></html>"#,
+                        redirect_uri,
+                    ))
+                }),
+            )
+            .route(
+                "/header",
+                get(move || async move {
+                    (
+                        [(LINK, format!(r#"<{}>; rel="redirect_uri""#, redirect_uri))],
+                        Html(""),
+                    )
+                }),
+            )
+            .route("/none", get(|| async { Html("<html></html>") }));
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://localhost:{}", listener.local_addr().unwrap().port());
+
+        tokio::spawn(
+            Server::from_tcp(listener)
+                .unwrap()
+                .serve(router.into_make_service()),
+        );
+
+        base_url
+    }
+
+    fn authorization_request(client_id: &str, redirect_uri: &str) -> AuthorizationRequest {
+        AuthorizationRequest {
+            response_type: "code".to_owned(),
+            client_id: client_id.to_owned(),
+            redirect_uri: redirect_uri.to_owned(),
+            state: String::new(),
+            code_challenge: "challenge".to_owned(),
+            code_challenge_method: "S256".to_owned(),
+            scope: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_redirect_uri_on_client_origin() {
+        // Nothing is fetched, so the client doesn't need to exist.
+        assert!(authorization_request(CLIENT_ID, REDIRECT_URI)
+            .verify_redirect_uri(&Client::new())
+            .await
+            .is_ok());
+
+        assert!(
+            authorization_request(CLIENT_ID, "http://app.example/callback")
+                .verify_redirect_uri(&Client::new())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_redirect_uri_published_by_client() {
+        let base_url = start_client().await;
+
+        for path in ["/json", "/html", "/header"] {
+            let client_id = format!("{}{}", base_url, path);
+
+            assert!(
+                authorization_request(&client_id, "https://callback.example/")
+                    .verify_redirect_uri(&Client::new())
+                    .await
+                    .is_ok()
+            );
+            assert!(
+                authorization_request(&client_id, "https://attacker.example/")
+                    .verify_redirect_uri(&Client::new())
+                    .await
+                    .is_err()
+            );
+        }
+
+        assert!(
+            authorization_request(&format!("{}/none", base_url), "https://callback.example/",)
+                .verify_redirect_uri(&Client::new())
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn validate_accepts_partial_post_scopes() {
+        let mut request = authorization_request(CLIENT_ID, REDIRECT_URI);
+
+        for scope in [
+            "profile create update delete",
+            "create",
+            "create media",
+            "update",
+        ] {
+            request.scope = scope.to_owned();
+            assert!(request.validate().is_ok());
+        }
+    }
+}
@@ -1,18 +1,29 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 // Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
+mod activitypub;
 mod admin;
+mod announce;
+mod api;
+mod indieauth;
+mod markdown;
+mod micropub;
+mod search;
+mod site;
+mod spam;
+mod webmention;
 
-use std::env;
+use std::{env, net::SocketAddr};
 
 use askama::Template;
 use axum::{
-    http::StatusCode,
+    http::{header::HeaderName, StatusCode},
     response::{Html, IntoResponse, Response},
     Extension, Router, Server,
 };
 use entity::{prelude::Settings, settings};
 use migration::{Migrator, MigratorTrait};
+use rand::{distributions::Alphanumeric, Rng};
 use sea_orm::{Database, DatabaseConnection, EntityTrait};
 
 const ADMIN_URL_PREFIX: &str = "/-";
@@ -55,6 +66,15 @@ async fn settings(connection: &DatabaseConnection) -> Result<settings::Model, Er
         ))
 }
 
+/// Generates a random string suitable for use as a secret token.
+fn random_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
 #[tokio::main]
 async fn main() {
     let database_url =
@@ -64,16 +84,42 @@ async fn main() {
         .await
         .expect("unable to connect to database");
 
+    // Behind a reverse proxy, the name of a header the proxy sets
+    // to the client's address, e.g. `X-Forwarded-For` or `X-Real-IP`.
+    // Without it, all visitors share the proxy's address for rate limiting.
+    let client_ip_header = env::var("CLIENT_IP_HEADER").ok().map(|name| {
+        HeaderName::from_bytes(name.as_bytes())
+            .expect("invalid header name in environment variable CLIENT_IP_HEADER")
+    });
+
     Migrator::up(&database_connection, None)
         .await
         .expect("unable to apply database migrations");
 
+    activitypub::generate_actor_key(&database_connection)
+        .await
+        .expect("unable to generate ActivityPub key");
+
+    tokio::spawn(announce::announce_scheduled_posts(
+        database_connection.clone(),
+    ));
+
     let router = Router::new()
+        .merge(site::router())
+        .merge(micropub::router())
+        .merge(indieauth::router())
+        .merge(webmention::router())
+        .merge(activitypub::router())
         .nest(ADMIN_URL_PREFIX, admin::router())
-        .layer(Extension(database_connection));
+        .nest(api::API_URL_PREFIX, api::router())
+        .layer(Extension(database_connection))
+        .layer(Extension(spam::SpamFilter::new(client_ip_header)))
+        .layer(Extension(activitypub::PublicKeyCache::new()));
 
     Server::bind(&"0.0.0.0:3000".parse().unwrap())
-        .serve(router.into_make_service())
+        // The client address is needed for rate limiting public forms,
+        // unless it is taken from `CLIENT_IP_HEADER`.
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 }
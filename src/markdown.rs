@@ -0,0 +1,144 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use pulldown_cmark::{escape::escape_href, html::push_html, CowStr, Event, Options, Parser, Tag};
+
+/// Renders Markdown written by users of the admin interface,
+/// who are trusted and may therefore include arbitrary HTML.
+pub(crate) fn markdown_to_html(markdown: &str) -> String {
+    let parser = Parser::new_ext(markdown, Options::all());
+
+    let mut html = String::new();
+    push_html(&mut html, parser);
+
+    html
+}
+
+/// Returns whether `url` is safe to link to from untrusted content,
+/// which rules out schemes like `javascript:`.
+fn is_safe_link(url: &str) -> bool {
+    let url = url.trim_start().to_lowercase();
+
+    url.starts_with("http://") || url.starts_with("https://") || url.starts_with("mailto:")
+}
+
+/// Renders Markdown written by visitors, such as comments.
+/// Raw HTML is removed, images are not displayed, and links are marked as user-generated,
+/// which discourages spammers and prevents the content from affecting the site.
+pub(crate) fn restricted_markdown_to_html(markdown: &str) -> String {
+    let parser = Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH).filter_map(|event| {
+        match event {
+            Event::Html(_) => None,
+            // Only the alternative text of images is kept.
+            Event::Start(Tag::Image(..)) | Event::End(Tag::Image(..)) => None,
+            Event::Start(Tag::Link(_, url, _)) => {
+                if is_safe_link(&url) {
+                    let mut html = String::from("<a href=\"");
+                    escape_href(&mut html, &url).unwrap();
+                    html.push_str("\" rel=\"nofollow ugc\">");
+                    Some(Event::Html(CowStr::from(html)))
+                } else {
+                    None
+                }
+            }
+            Event::End(Tag::Link(_, url, _)) => {
+                if is_safe_link(&url) {
+                    Some(Event::Html(CowStr::Borrowed("</a>")))
+                } else {
+                    None
+                }
+            }
+            event => Some(event),
+        }
+    });
+
+    let mut html = String::new();
+    push_html(&mut html, parser);
+
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restricted_markdown_removes_html() {
+        assert_eq!(restricted_markdown_to_html("<script>alert(1)</script>"), "");
+        assert_eq!(
+            restricted_markdown_to_html("Hi <script>alert(1)</script> there"),
+            "<p>Hi alert(1) there</p>\n",
+        );
+        assert_eq!(
+            restricted_markdown_to_html("<img src=x onerror=alert(1)>"),
+            "",
+        );
+        assert_eq!(
+            restricted_markdown_to_html("a <img src=x onerror=alert(1)> b"),
+            "<p>a  b</p>\n",
+        );
+    }
+
+    #[test]
+    fn restricted_markdown_keeps_text_of_unsafe_links() {
+        for markdown in [
+            "[x](javascript:alert(1))",
+            "[x]( JAVASCRIPT:alert(1))",
+            "[x](java&#x73;cript:alert(1))",
+            "[x](data:text/html;base64,PHNjcmlwdD4=)",
+            "[x][r]\n\n[r]: javascript:alert(1)",
+        ] {
+            assert_eq!(
+                restricted_markdown_to_html(markdown),
+                "<p>x</p>\n",
+                "{}",
+                markdown
+            );
+        }
+
+        assert_eq!(
+            restricted_markdown_to_html("<javascript:alert(1)>"),
+            "<p>javascript:alert(1)</p>\n",
+        );
+    }
+
+    #[test]
+    fn restricted_markdown_marks_links_as_user_generated() {
+        assert_eq!(
+            restricted_markdown_to_html("[x](https://example.com/a)"),
+            "<p><a href=\"https://example.com/a\" rel=\"nofollow ugc\">x</a></p>\n",
+        );
+        assert_eq!(
+            restricted_markdown_to_html("<https://example.com/a>"),
+            "<p><a href=\"https://example.com/a\" rel=\"nofollow ugc\">https://example.com/a</a></p>\n",
+        );
+        assert_eq!(
+            restricted_markdown_to_html("[x][r]\n\n[r]: https://example.com/r"),
+            "<p><a href=\"https://example.com/r\" rel=\"nofollow ugc\">x</a></p>\n",
+        );
+    }
+
+    #[test]
+    fn restricted_markdown_escapes_quotes_in_links() {
+        assert_eq!(
+            restricted_markdown_to_html("[x](<https://example.com/\" onmouseover=\"alert(1)>)"),
+            "<p><a href=\"https://example.com/%22%20onmouseover=%22alert(1)\" rel=\"nofollow ugc\">x</a></p>\n",
+        );
+        assert_eq!(
+            restricted_markdown_to_html("[x](https://example.com/'><script>)"),
+            "<p><a href=\"https://example.com/&#x27;%3E%3Cscript%3E\" rel=\"nofollow ugc\">x</a></p>\n",
+        );
+    }
+
+    #[test]
+    fn restricted_markdown_reduces_images_to_alternative_text() {
+        assert_eq!(
+            restricted_markdown_to_html("![alt *text*](https://example.com/i.png)"),
+            "<p>alt <em>text</em></p>\n",
+        );
+        assert_eq!(
+            restricted_markdown_to_html("![<b>alt</b>](https://example.com/i.png \"t\")"),
+            "<p>alt</p>\n",
+        );
+    }
+}
@@ -0,0 +1,1305 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+//! Micropub endpoint (https://www.w3.org/TR/micropub/), which allows
+//! third-party clients to create, update and delete posts.
+//! Clients authenticate with API tokens.
+
+use axum::{
+    body::Bytes,
+    extract::{Host, Query},
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE, LOCATION},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Json, Router,
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use entity::{page, prelude::Tag, sea_orm_active_enums::ApiTokenScope, user};
+use sea_orm::{ColumnTrait, DatabaseConnection, ModelTrait, QueryFilter};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    admin::{
+        posts::{delete_post, editable_posts, save_post, PostInput},
+        title_to_url,
+    },
+    announce::announce_post,
+    api::{bearer_token, token_user},
+    settings,
+    site::base_url,
+    ErrorResponse,
+};
+
+pub(super) const MICROPUB_URL: &str = "/micropub";
+
+pub(super) fn router() -> Router {
+    Router::new().route(MICROPUB_URL, get(get_micropub).post(post_micropub))
+}
+
+/// Error in the format defined by the Micropub specification.
+#[derive(Debug)]
+struct MicropubError {
+    status: StatusCode,
+    error: &'static str,
+    description: &'static str,
+}
+
+impl From<ErrorResponse> for MicropubError {
+    fn from((status, description): ErrorResponse) -> Self {
+        MicropubError {
+            status,
+            error: match status {
+                StatusCode::UNAUTHORIZED => "unauthorized",
+                StatusCode::FORBIDDEN => "forbidden",
+                status if status.is_server_error() => "server_error",
+                _ => "invalid_request",
+            },
+            description,
+        }
+    }
+}
+
+impl IntoResponse for MicropubError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(json!({
+                "error": self.error,
+                "error_description": self.description,
+            })),
+        )
+            .into_response()
+    }
+}
+
+fn invalid_request(description: &'static str) -> MicropubError {
+    (StatusCode::BAD_REQUEST, description).into()
+}
+
+/// Authenticates the request using the token from the `Authorization` header,
+/// or, if there is none, the `access_token` parameter. Unless `scopes` is empty,
+/// the token must have one of them.
+async fn authenticate(
+    connection: &DatabaseConnection,
+    headers: &HeaderMap,
+    access_token: Option<&str>,
+    scopes: &[ApiTokenScope],
+) -> Result<user::Model, MicropubError> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(bearer_token)
+        .or(access_token)
+        .ok_or((StatusCode::UNAUTHORIZED, "missing access token"))?;
+
+    let (user, token_scope) = token_user(connection, token)
+        .await?
+        .ok_or((StatusCode::UNAUTHORIZED, "invalid access token"))?;
+
+    if scopes.is_empty() || scopes.contains(&token_scope) {
+        Ok(user)
+    } else {
+        Err(MicropubError {
+            status: StatusCode::FORBIDDEN,
+            error: "insufficient_scope",
+            description: "access token does not have the required scope",
+        })
+    }
+}
+
+/// Finds the post a client refers to by its full URL.
+async fn post_by_url(
+    connection: &DatabaseConnection,
+    user: &user::Model,
+    url: &str,
+) -> Result<page::Model, MicropubError> {
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    let slug = url
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+
+    Ok(editable_posts(user)
+        .filter(page::Column::Url.eq(slug))
+        .one(connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve post"))?
+        .ok_or((StatusCode::BAD_REQUEST, "post not found"))?)
+}
+
+async fn post_tags(
+    connection: &DatabaseConnection,
+    post: &page::Model,
+) -> Result<Vec<String>, MicropubError> {
+    Ok(post
+        .find_related(Tag)
+        .all(connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve tags"))?
+        .into_iter()
+        .map(|tag| tag.name)
+        .collect())
+}
+
+async fn post_url(
+    connection: &DatabaseConnection,
+    host: &str,
+    post: &page::Model,
+) -> Result<String, MicropubError> {
+    Ok(format!(
+        "{}/{}",
+        base_url(&settings(connection).await?, host),
+        post.url,
+    ))
+}
+
+/// Returns the parameters named `name`, where `name[]` is treated the same as `name`.
+fn parameters<'a>(
+    pairs: &'a [(String, String)],
+    name: &'a str,
+) -> impl Iterator<Item = &'a str> + 'a {
+    pairs
+        .iter()
+        .filter(move |(key, _)| key.strip_suffix("[]").unwrap_or(key) == name)
+        .map(|(_, value)| value.as_str())
+}
+
+fn parameter<'a>(pairs: &'a [(String, String)], name: &'a str) -> Option<&'a str> {
+    parameters(pairs, name).next()
+}
+
+async fn get_micropub(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    headers: HeaderMap,
+    Host(host): Host,
+    Query(query): Query<Vec<(String, String)>>,
+) -> Result<impl IntoResponse, MicropubError> {
+    let user = authenticate(database_connection, &headers, None, &[]).await?;
+
+    match parameter(&query, "q") {
+        Some("config") => Ok(Json(json!({
+            "q": ["config", "source", "syndicate-to"],
+            "syndicate-to": [],
+        }))),
+        Some("syndicate-to") => Ok(Json(json!({ "syndicate-to": [] }))),
+        Some("source") => {
+            let post = post_by_url(
+                database_connection,
+                &user,
+                parameter(&query, "url").ok_or_else(|| invalid_request("missing URL"))?,
+            )
+            .await?;
+
+            let mut properties = json!({
+                "name": [post.title],
+                "content": [post.content_markdown],
+                "category": post_tags(database_connection, &post).await?,
+                "published": [post.time.format("%Y-%m-%dT%H:%M:%SZ").to_string()],
+                "mp-slug": [post.url],
+                "post-status": [if post.is_published { "published" } else { "draft" }],
+                "url": [post_url(database_connection, &host, &post).await?],
+            });
+
+            let requested_properties: Vec<&str> = parameters(&query, "properties").collect();
+
+            // As required by the specification, the type is omitted
+            // if specific properties are requested.
+            Ok(Json(if requested_properties.is_empty() {
+                json!({
+                    "type": ["h-entry"],
+                    "properties": properties,
+                })
+            } else {
+                properties
+                    .as_object_mut()
+                    .unwrap()
+                    .retain(|name, _| requested_properties.contains(&name.as_str()));
+
+                json!({ "properties": properties })
+            }))
+        }
+        _ => Err(invalid_request("unsupported query")),
+    }
+}
+
+/// Micropub request, normalized from either form-encoded or JSON syntax.
+/// Properties map names to lists of values, as in JSON syntax.
+#[derive(Debug)]
+enum MicropubRequest {
+    Create {
+        properties: Map<String, Value>,
+    },
+    Update {
+        url: String,
+        replace: Map<String, Value>,
+        add: Map<String, Value>,
+        delete: Value,
+    },
+    Delete {
+        url: String,
+    },
+}
+
+fn form_request(form: &[(String, String)]) -> Result<MicropubRequest, MicropubError> {
+    match parameter(form, "action") {
+        Some("delete") => Ok(MicropubRequest::Delete {
+            url: parameter(form, "url")
+                .ok_or_else(|| invalid_request("missing URL"))?
+                .to_owned(),
+        }),
+        Some(_) => Err(invalid_request(
+            "unsupported action, updates require JSON syntax",
+        )),
+        None => {
+            if parameter(form, "h").unwrap_or("entry") != "entry" {
+                return Err(invalid_request(
+                    "unsupported type, only entries are supported",
+                ));
+            }
+
+            let mut properties = Map::new();
+
+            for (key, value) in form {
+                let key = key.strip_suffix("[]").unwrap_or(key);
+
+                if !["h", "access_token", "action", "url"].contains(&key) {
+                    properties
+                        .entry(key)
+                        .or_insert_with(|| Value::Array(Vec::new()))
+                        .as_array_mut()
+                        .unwrap()
+                        .push(Value::String(value.clone()));
+                }
+            }
+
+            Ok(MicropubRequest::Create { properties })
+        }
+    }
+}
+
+fn json_request(json: Value) -> Result<MicropubRequest, MicropubError> {
+    let object = |value: Option<&Value>| match value {
+        Some(Value::Object(object)) => Ok(object.clone()),
+        None => Ok(Map::new()),
+        Some(_) => Err(invalid_request("invalid JSON request body")),
+    };
+
+    let url = || {
+        json.get("url")
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+            .ok_or_else(|| invalid_request("missing URL"))
+    };
+
+    match json.get("action").and_then(Value::as_str) {
+        Some("update") => Ok(MicropubRequest::Update {
+            url: url()?,
+            replace: object(json.get("replace"))?,
+            add: object(json.get("add"))?,
+            delete: json.get("delete").cloned().unwrap_or(Value::Null),
+        }),
+        Some("delete") => Ok(MicropubRequest::Delete { url: url()? }),
+        Some(_) => Err(invalid_request("unsupported action")),
+        None => {
+            if json.get("type") != Some(&json!(["h-entry"])) {
+                return Err(invalid_request(
+                    "unsupported type, only entries are supported",
+                ));
+            }
+
+            Ok(MicropubRequest::Create {
+                properties: object(json.get("properties"))?,
+            })
+        }
+    }
+}
+
+/// Returns the text values of a property. HTML content is accepted as well,
+/// because it is valid Markdown.
+fn texts(values: &Value) -> Vec<String> {
+    values
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|value| match value {
+            Value::String(text) => Some(text.clone()),
+            Value::Object(object) => object
+                .get("html")
+                .or_else(|| object.get("value"))
+                .and_then(Value::as_str)
+                .map(str::to_owned),
+            _ => None,
+        })
+        .collect()
+}
+
+fn text(values: &Value) -> String {
+    texts(values).into_iter().next().unwrap_or_default()
+}
+
+/// Parses an ISO 8601 date and time. Times without a time zone are taken to be UTC.
+/// A date alone leaves the time unset, so that the post gets the same default time
+/// as one created in the admin interface.
+fn parse_published(published: &str) -> Result<(NaiveDate, Option<NaiveTime>), MicropubError> {
+    DateTime::parse_from_rfc3339(published)
+        .map(|time| time.naive_utc())
+        .or_else(|_| NaiveDateTime::parse_from_str(published, "%Y-%m-%dT%H:%M:%S"))
+        .or_else(|_| NaiveDateTime::parse_from_str(published, "%Y-%m-%dT%H:%M"))
+        .map(|time| (time.date(), Some(time.time())))
+        .or_else(|_| NaiveDate::parse_from_str(published, "%Y-%m-%d").map(|date| (date, None)))
+        .map_err(|_| invalid_request("invalid published date"))
+}
+
+/// Sets the fields of `post_input` from `properties`, replacing their previous values.
+/// Returns the publication status if it was set as well.
+fn replace_properties(
+    post_input: &mut PostInput,
+    properties: &Map<String, Value>,
+) -> Result<Option<bool>, MicropubError> {
+    let mut is_published = None;
+
+    // Other properties are ignored, as permitted by the specification.
+    for (name, values) in properties {
+        match name.as_str() {
+            "name" => post_input.title = text(values),
+            "content" => post_input.content = text(values),
+            "category" => post_input.tags = texts(values).join(","),
+            "mp-slug" => post_input.url = text(values),
+            "published" => {
+                let (date, time) = parse_published(&text(values))?;
+                post_input.date = date.format("%Y-%m-%d").to_string();
+                post_input.time = time
+                    .map(|time| time.format("%H:%M:%S").to_string())
+                    .unwrap_or_default();
+            }
+            "post-status" => {
+                is_published = Some(match text(values).as_str() {
+                    "published" => true,
+                    "draft" => false,
+                    _ => return Err(invalid_request("invalid post status")),
+                })
+            }
+            _ => {}
+        }
+    }
+
+    Ok(is_published)
+}
+
+/// Applies the changes from an update request to `post_input`, whose tags are given
+/// separately as `tags`. Returns the publication status if it was set as well.
+fn apply_update(
+    post_input: &mut PostInput,
+    mut tags: Vec<String>,
+    replace: &Map<String, Value>,
+    add: &Map<String, Value>,
+    delete: Value,
+) -> Result<Option<bool>, MicropubError> {
+    match delete {
+        Value::Null => {}
+        Value::Array(names) => {
+            for name in names {
+                match name.as_str() {
+                    Some("category") => tags.clear(),
+                    Some("name") => post_input.title.clear(),
+                    Some("content") => post_input.content.clear(),
+                    _ => return Err(invalid_request("cannot delete property")),
+                }
+            }
+        }
+        Value::Object(properties) => {
+            for (name, values) in properties {
+                if name != "category" {
+                    return Err(invalid_request("cannot delete values of property"));
+                }
+
+                let deleted_tags: Vec<String> =
+                    texts(&values).iter().map(|tag| title_to_url(tag)).collect();
+
+                tags.retain(|tag| !deleted_tags.contains(tag));
+            }
+        }
+        _ => return Err(invalid_request("invalid JSON request body")),
+    }
+
+    for (name, values) in add {
+        if name != "category" {
+            return Err(invalid_request("cannot add values to property"));
+        }
+
+        tags.extend(texts(values));
+    }
+
+    post_input.tags = tags.join(",");
+
+    // Replacing categories overrides the changes above.
+    replace_properties(post_input, replace)
+}
+
+async fn post_micropub(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    headers: HeaderMap,
+    Host(host): Host,
+    body: Bytes,
+) -> Result<Response, MicropubError> {
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .unwrap_or_default();
+
+    let (request, access_token) = if content_type.starts_with("application/json") {
+        let json = serde_json::from_slice(&body)
+            .map_err(|_| invalid_request("invalid JSON request body"))?;
+
+        (json_request(json)?, None)
+    } else if content_type.starts_with("application/x-www-form-urlencoded") {
+        let form: Vec<(String, String)> = serde_urlencoded::from_bytes(&body)
+            .map_err(|_| invalid_request("invalid form request body"))?;
+
+        (
+            form_request(&form)?,
+            parameter(&form, "access_token").map(str::to_owned),
+        )
+    } else {
+        return Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "unsupported content type, must be form-encoded or JSON",
+        )
+            .into());
+    };
+
+    let scopes: &[ApiTokenScope] = match request {
+        MicropubRequest::Create { .. } => &[ApiTokenScope::PostsWrite, ApiTokenScope::PostsCreate],
+        _ => &[ApiTokenScope::PostsWrite],
+    };
+
+    let user = authenticate(
+        database_connection,
+        &headers,
+        access_token.as_deref(),
+        scopes,
+    )
+    .await?;
+
+    match request {
+        MicropubRequest::Create { properties } => {
+            let mut post_input = PostInput {
+                title: String::new(),
+                url: String::new(),
+                date: String::new(),
+                time: String::new(),
+                tags: String::new(),
+                content: String::new(),
+            };
+
+            let is_published = replace_properties(&mut post_input, &properties)?;
+
+            // Notes usually have no name from which a URL could be derived.
+            if post_input.url.is_empty() && title_to_url(&post_input.title).is_empty() {
+                post_input.url = Utc::now().format("%Y-%m-%d-%H%M%S").to_string();
+            }
+
+            // Micropub clients expect posts to be published unless they say otherwise.
+            let is_published = is_published.unwrap_or(true);
+
+            // New posts are drafts, which authors may create as well.
+            let post = save_post(
+                database_connection,
+                &user,
+                None,
+                &post_input,
+                is_published.then_some(true),
+            )
+            .await?;
+
+            if is_published {
+                announce_post(database_connection, &post).await?;
+            }
+
+            Ok((
+                StatusCode::CREATED,
+                [(LOCATION, post_url(database_connection, &host, &post).await?)],
+            )
+                .into_response())
+        }
+        MicropubRequest::Update {
+            url,
+            replace,
+            add,
+            delete,
+        } => {
+            let post = post_by_url(database_connection, &user, &url).await?;
+            let tags = post_tags(database_connection, &post).await?;
+
+            let mut post_input = PostInput {
+                title: post.title.clone(),
+                url: post.url.clone(),
+                date: post.time.format("%Y-%m-%d").to_string(),
+                time: post.time.format("%H:%M:%S").to_string(),
+                tags: String::new(),
+                content: post.content_markdown.clone(),
+            };
+
+            // Restating the current status changes nothing, and is allowed for authors.
+            let is_published = apply_update(&mut post_input, tags, &replace, &add, delete)?
+                .filter(|is_published| *is_published != post.is_published);
+
+            let updated_post = save_post(
+                database_connection,
+                &user,
+                Some(post.id),
+                &post_input,
+                is_published,
+            )
+            .await?;
+
+            if is_published == Some(true) {
+                announce_post(database_connection, &updated_post).await?;
+            }
+
+            // The specification requires telling the client if the URL has changed.
+            if updated_post.url == post.url {
+                Ok(StatusCode::NO_CONTENT.into_response())
+            } else {
+                Ok((
+                    StatusCode::CREATED,
+                    [(
+                        LOCATION,
+                        post_url(database_connection, &host, &updated_post).await?,
+                    )],
+                )
+                    .into_response())
+            }
+        }
+        MicropubRequest::Delete { url } => {
+            let post = post_by_url(database_connection, &user, &url).await?;
+
+            delete_post(database_connection, &user, post.id).await?;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header::HOST, Request},
+    };
+    use entity::{api_token, page_tag, prelude::Page, sea_orm_active_enums::Role};
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{ActiveModelTrait, Database, EntityTrait, Set};
+    use tower::ServiceExt;
+
+    use crate::admin::api_tokens::hash_api_token;
+
+    use super::*;
+
+    fn form(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn empty_post_input() -> PostInput {
+        PostInput {
+            title: String::new(),
+            url: String::new(),
+            date: String::new(),
+            time: String::new(),
+            tags: String::new(),
+            content: String::new(),
+        }
+    }
+
+    fn properties(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn form_request_collects_properties() {
+        let request = form_request(&form(&[
+            ("h", "entry"),
+            ("access_token", "secret"),
+            ("name", "Title"),
+            ("content", "Text"),
+            ("category[]", "one"),
+            ("category[]", "two"),
+        ]))
+        .unwrap();
+
+        let MicropubRequest::Create { properties } = request else {
+            panic!("expected create request");
+        };
+
+        assert_eq!(
+            Value::Object(properties),
+            json!({
+                "name": ["Title"],
+                "content": ["Text"],
+                "category": ["one", "two"],
+            }),
+        );
+    }
+
+    #[test]
+    fn form_request_treats_array_names_like_plain_names() {
+        let request = form_request(&form(&[("name[]", "Title"), ("category", "one")])).unwrap();
+
+        let MicropubRequest::Create { properties } = request else {
+            panic!("expected create request");
+        };
+
+        assert_eq!(properties["name"], json!(["Title"]));
+        assert_eq!(properties["category"], json!(["one"]));
+    }
+
+    #[test]
+    fn form_request_delete() {
+        let request = form_request(&form(&[
+            ("action", "delete"),
+            ("url", "https://example.com/post"),
+        ]))
+        .unwrap();
+
+        let MicropubRequest::Delete { url } = request else {
+            panic!("expected delete request");
+        };
+
+        assert_eq!(url, "https://example.com/post");
+    }
+
+    #[test]
+    fn form_request_rejects_unsupported_requests() {
+        assert!(form_request(&form(&[("h", "card")])).is_err());
+        assert!(form_request(&form(&[("action", "update"), ("url", "x")])).is_err());
+        assert!(form_request(&form(&[("action", "delete")])).is_err());
+    }
+
+    #[test]
+    fn json_request_create() {
+        let request = json_request(json!({
+            "type": ["h-entry"],
+            "properties": {
+                "content": [{"html": "<p>Text</p>"}],
+            },
+        }))
+        .unwrap();
+
+        let MicropubRequest::Create { properties } = request else {
+            panic!("expected create request");
+        };
+
+        assert_eq!(text(&properties["content"]), "<p>Text</p>");
+    }
+
+    #[test]
+    fn json_request_update() {
+        let request = json_request(json!({
+            "action": "update",
+            "url": "https://example.com/post",
+            "add": {"category": ["new"]},
+            "delete": ["name"],
+        }))
+        .unwrap();
+
+        let MicropubRequest::Update {
+            url,
+            replace,
+            add,
+            delete,
+        } = request
+        else {
+            panic!("expected update request");
+        };
+
+        assert_eq!(url, "https://example.com/post");
+        assert!(replace.is_empty());
+        assert_eq!(add["category"], json!(["new"]));
+        assert_eq!(delete, json!(["name"]));
+    }
+
+    #[test]
+    fn json_request_rejects_unsupported_requests() {
+        assert!(json_request(json!({"type": ["h-card"], "properties": {}})).is_err());
+        assert!(json_request(json!({"action": "undelete", "url": "x"})).is_err());
+        assert!(json_request(json!({"action": "update"})).is_err());
+        assert!(json_request(json!({"action": "update", "url": "x", "add": []})).is_err());
+    }
+
+    #[test]
+    fn parse_published_formats() {
+        let date = NaiveDate::from_ymd_opt(2023, 1, 15).unwrap();
+        let time = |text| Some(NaiveTime::parse_from_str(text, "%H:%M:%S").unwrap());
+
+        assert_eq!(
+            parse_published("2023-01-15T10:30:00+02:00").unwrap(),
+            (date, time("08:30:00")),
+        );
+        assert_eq!(
+            parse_published("2023-01-15T10:30:00Z").unwrap(),
+            (date, time("10:30:00")),
+        );
+        assert_eq!(
+            parse_published("2023-01-15T10:30:15").unwrap(),
+            (date, time("10:30:15")),
+        );
+        assert_eq!(
+            parse_published("2023-01-15T10:30").unwrap(),
+            (date, time("10:30:00")),
+        );
+        assert_eq!(parse_published("2023-01-15").unwrap(), (date, None));
+        assert!(parse_published("15 January 2023").is_err());
+    }
+
+    #[test]
+    fn replace_properties_sets_fields() {
+        let mut post_input = empty_post_input();
+
+        let is_published = replace_properties(
+            &mut post_input,
+            &properties(json!({
+                "name": ["Title"],
+                "content": [{"value": "Text"}],
+                "category": ["one", "two"],
+                "mp-slug": ["slug"],
+                "published": ["2023-01-15T10:30:00Z"],
+                "post-status": ["draft"],
+                "location": ["geo:0,0"],
+            })),
+        )
+        .unwrap();
+
+        assert_eq!(is_published, Some(false));
+        assert_eq!(post_input.title, "Title");
+        assert_eq!(post_input.content, "Text");
+        assert_eq!(post_input.tags, "one,two");
+        assert_eq!(post_input.url, "slug");
+        assert_eq!(post_input.date, "2023-01-15");
+        assert_eq!(post_input.time, "10:30:00");
+    }
+
+    #[test]
+    fn replace_properties_leaves_time_of_date_blank() {
+        let mut post_input = empty_post_input();
+        post_input.time = "10:30:00".to_owned();
+
+        replace_properties(
+            &mut post_input,
+            &properties(json!({"published": ["2023-01-15"]})),
+        )
+        .unwrap();
+
+        assert_eq!(post_input.date, "2023-01-15");
+        assert_eq!(post_input.time, "");
+    }
+
+    #[test]
+    fn replace_properties_without_status() {
+        let mut post_input = empty_post_input();
+
+        let is_published =
+            replace_properties(&mut post_input, &properties(json!({"name": ["Title"]}))).unwrap();
+
+        assert_eq!(is_published, None);
+    }
+
+    #[test]
+    fn replace_properties_rejects_invalid_values() {
+        let mut post_input = empty_post_input();
+
+        assert!(replace_properties(
+            &mut post_input,
+            &properties(json!({"post-status": ["scheduled"]})),
+        )
+        .is_err());
+        assert!(replace_properties(
+            &mut post_input,
+            &properties(json!({"published": ["yesterday"]})),
+        )
+        .is_err());
+    }
+
+    fn update_tags(replace: Value, add: Value, delete: Value) -> Result<String, MicropubError> {
+        let mut post_input = empty_post_input();
+
+        apply_update(
+            &mut post_input,
+            vec!["one".to_owned(), "two".to_owned()],
+            &properties(replace),
+            &properties(add),
+            delete,
+        )?;
+
+        Ok(post_input.tags)
+    }
+
+    #[test]
+    fn update_adds_categories() {
+        assert_eq!(
+            update_tags(json!({}), json!({"category": ["three"]}), Value::Null).unwrap(),
+            "one,two,three",
+        );
+    }
+
+    #[test]
+    fn update_deletes_category_values() {
+        assert_eq!(
+            update_tags(json!({}), json!({}), json!({"category": ["One"]})).unwrap(),
+            "two",
+        );
+    }
+
+    #[test]
+    fn update_deletes_category_property() {
+        assert_eq!(
+            update_tags(
+                json!({}),
+                json!({"category": ["three"]}),
+                json!(["category"])
+            )
+            .unwrap(),
+            "three",
+        );
+    }
+
+    #[test]
+    fn update_replaces_categories() {
+        assert_eq!(
+            update_tags(
+                json!({"category": ["four"]}),
+                json!({"category": ["three"]}),
+                json!({"category": ["one"]}),
+            )
+            .unwrap(),
+            "four",
+        );
+    }
+
+    #[test]
+    fn update_rejects_unsupported_changes() {
+        assert!(update_tags(json!({}), json!({"name": ["Title"]}), Value::Null).is_err());
+        assert!(update_tags(json!({}), json!({}), json!({"name": ["Title"]})).is_err());
+        assert!(update_tags(json!({}), json!({}), json!(["published"])).is_err());
+        assert!(update_tags(json!({}), json!({}), json!("category")).is_err());
+    }
+
+    const HOST_NAME: &str = "blog.example.com";
+
+    /// Returns a database with an editor who has a token for writing posts,
+    /// a token for reading only and a token for creating posts only, and an author with a token for writing posts.
+    async fn database_with_tokens() -> DatabaseConnection {
+        let connection = Database::connect("sqlite::memory:").await.unwrap();
+
+        Migrator::up(&connection, None).await.unwrap();
+
+        let mut user_ids = Vec::new();
+
+        for (name, role) in [("jane", Role::Editor), ("joe", Role::Author)] {
+            let user = user::ActiveModel {
+                name: Set(name.to_owned()),
+                password_hash: Set(String::new()),
+                role: Set(role),
+                ..Default::default()
+            }
+            .insert(&connection)
+            .await
+            .unwrap();
+
+            user_ids.push(user.id);
+        }
+
+        for (user_id, token, scope) in [
+            (user_ids[0], "write-token", ApiTokenScope::PostsWrite),
+            (user_ids[0], "read-token", ApiTokenScope::Read),
+            (user_ids[0], "create-token", ApiTokenScope::PostsCreate),
+            (user_ids[1], "author-token", ApiTokenScope::PostsWrite),
+        ] {
+            api_token::ActiveModel {
+                user_id: Set(user_id),
+                name: Set(token.to_owned()),
+                token_hash: Set(hash_api_token(token)),
+                scope: Set(scope),
+                created: Set(Utc::now().naive_utc()),
+                ..Default::default()
+            }
+            .insert(&connection)
+            .await
+            .unwrap();
+        }
+
+        connection
+    }
+
+    async fn send_request(
+        connection: &DatabaseConnection,
+        request: axum::http::request::Builder,
+        body: Body,
+    ) -> Response {
+        router()
+            .layer(Extension(connection.clone()))
+            .oneshot(request.header(HOST, HOST_NAME).body(body).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn get(connection: &DatabaseConnection, query: &str, token: Option<&str>) -> Response {
+        let mut request = Request::get(format!("{}?{}", MICROPUB_URL, query));
+
+        if let Some(token) = token {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+
+        send_request(connection, request, Body::empty()).await
+    }
+
+    async fn post_form(connection: &DatabaseConnection, form: &[(&str, &str)]) -> Response {
+        send_request(
+            connection,
+            Request::post(MICROPUB_URL).header(CONTENT_TYPE, "application/x-www-form-urlencoded"),
+            Body::from(serde_urlencoded::to_string(form).unwrap()),
+        )
+        .await
+    }
+
+    async fn post_json(connection: &DatabaseConnection, json: Value, token: &str) -> Response {
+        send_request(
+            connection,
+            Request::post(MICROPUB_URL)
+                .header(CONTENT_TYPE, "application/json")
+                .header(AUTHORIZATION, format!("Bearer {}", token)),
+            Body::from(json.to_string()),
+        )
+        .await
+    }
+
+    async fn json_body(response: Response) -> Value {
+        serde_json::from_slice(&hyper::body::to_bytes(response.into_body()).await.unwrap()).unwrap()
+    }
+
+    fn location(response: &Response) -> &str {
+        response.headers()[LOCATION].to_str().unwrap()
+    }
+
+    async fn posts(connection: &DatabaseConnection) -> Vec<page::Model> {
+        Page::find().all(connection).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn form_create() {
+        let connection = database_with_tokens().await;
+
+        let response = post_form(
+            &connection,
+            &[
+                ("h", "entry"),
+                ("name", "Hello"),
+                ("content", "Some *text*"),
+                ("category[]", "one"),
+                ("category[]", "two"),
+                ("access_token", "write-token"),
+            ],
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(location(&response), "http://blog.example.com/hello");
+
+        let posts = posts(&connection).await;
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].title, "Hello");
+        assert_eq!(posts[0].content_markdown, "Some *text*");
+        assert!(posts[0].is_post);
+        assert!(posts[0].is_published);
+        assert_eq!(
+            post_tags(&connection, &posts[0]).await.unwrap(),
+            ["one", "two"],
+        );
+    }
+
+    #[tokio::test]
+    async fn json_create() {
+        let connection = database_with_tokens().await;
+
+        let response = post_json(
+            &connection,
+            json!({
+                "type": ["h-entry"],
+                "properties": {
+                    "content": ["A note"],
+                    "mp-slug": ["note"],
+                    "post-status": ["draft"],
+                },
+            }),
+            "write-token",
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(location(&response), "http://blog.example.com/note");
+
+        let posts = posts(&connection).await;
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].content_markdown, "A note");
+        assert!(!posts[0].is_published);
+    }
+
+    #[tokio::test]
+    async fn update_and_delete() {
+        let connection = database_with_tokens().await;
+
+        post_form(
+            &connection,
+            &[
+                ("name", "Hello"),
+                ("content", "Text"),
+                ("category", "one"),
+                ("access_token", "write-token"),
+            ],
+        )
+        .await;
+
+        let url = "http://blog.example.com/hello";
+
+        let response = post_json(
+            &connection,
+            json!({
+                "action": "update",
+                "url": url,
+                "replace": {"name": ["Changed"]},
+                "add": {"category": ["two"]},
+            }),
+            "write-token",
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let post = posts(&connection).await.remove(0);
+        assert_eq!(post.title, "Changed");
+        assert_eq!(post.url, "hello");
+        assert_eq!(post_tags(&connection, &post).await.unwrap(), ["one", "two"]);
+
+        // A new slug moves the post.
+        let response = post_json(
+            &connection,
+            json!({
+                "action": "update",
+                "url": url,
+                "replace": {"mp-slug": ["moved"]},
+            }),
+            "write-token",
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(location(&response), "http://blog.example.com/moved");
+
+        let response = post_form(
+            &connection,
+            &[
+                ("action", "delete"),
+                ("url", "http://blog.example.com/moved"),
+                ("access_token", "write-token"),
+            ],
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(posts(&connection).await.is_empty());
+        assert!(page_tag::Entity::find()
+            .all(&connection)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(Tag::find().all(&connection).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn config_query() {
+        let connection = database_with_tokens().await;
+
+        let response = get(&connection, "q=config", Some("read-token")).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            json_body(response).await["q"],
+            json!(["config", "source", "syndicate-to"]),
+        );
+    }
+
+    #[tokio::test]
+    async fn source_query() {
+        let connection = database_with_tokens().await;
+
+        post_form(
+            &connection,
+            &[
+                ("name", "Hello"),
+                ("content", "Text"),
+                ("category", "one"),
+                ("published", "2023-01-15T10:30:00Z"),
+                ("access_token", "write-token"),
+            ],
+        )
+        .await;
+
+        let response = get(
+            &connection,
+            "q=source&url=http://blog.example.com/hello",
+            Some("read-token"),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            json_body(response).await,
+            json!({
+                "type": ["h-entry"],
+                "properties": {
+                    "name": ["Hello"],
+                    "content": ["Text"],
+                    "category": ["one"],
+                    "published": ["2023-01-15T10:30:00Z"],
+                    "mp-slug": ["hello"],
+                    "post-status": ["published"],
+                    "url": ["http://blog.example.com/hello"],
+                },
+            }),
+        );
+
+        let response = get(
+            &connection,
+            "q=source&url=http://blog.example.com/hello&properties[]=name",
+            Some("read-token"),
+        )
+        .await;
+
+        assert_eq!(
+            json_body(response).await,
+            json!({"properties": {"name": ["Hello"]}}),
+        );
+    }
+
+    #[tokio::test]
+    async fn requests_without_valid_token_are_rejected() {
+        let connection = database_with_tokens().await;
+
+        let response = get(&connection, "q=config", None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(json_body(response).await["error"], "unauthorized");
+
+        let response = get(&connection, "q=config", Some("wrong-token")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = post_form(&connection, &[("name", "Hello")]).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = post_json(
+            &connection,
+            json!({"type": ["h-entry"], "properties": {"name": ["Hello"]}}),
+            "read-token",
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(json_body(response).await["error"], "insufficient_scope");
+
+        assert!(posts(&connection).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn authors_create_drafts_only() {
+        let connection = database_with_tokens().await;
+
+        let response = post_form(
+            &connection,
+            &[
+                ("name", "Draft"),
+                ("content", "Text"),
+                ("post-status", "draft"),
+                ("access_token", "author-token"),
+            ],
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let drafts = posts(&connection).await;
+        assert_eq!(drafts.len(), 1);
+        assert!(!drafts[0].is_published);
+
+        // Saying that the draft is still a draft is not publishing it.
+        let response = post_json(
+            &connection,
+            json!({
+                "action": "update",
+                "url": "http://blog.example.com/draft",
+                "replace": {"content": ["Changed"], "post-status": ["draft"]},
+            }),
+            "author-token",
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        for response in [
+            post_form(
+                &connection,
+                &[("name", "Post"), ("access_token", "author-token")],
+            )
+            .await,
+            post_json(
+                &connection,
+                json!({
+                    "action": "update",
+                    "url": "http://blog.example.com/draft",
+                    "replace": {"post-status": ["published"]},
+                }),
+                "author-token",
+            )
+            .await,
+        ] {
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+
+        let posts = posts(&connection).await;
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].content_markdown, "Changed");
+        assert!(!posts[0].is_published);
+    }
+
+    #[tokio::test]
+    async fn create_scope_only_allows_creating() {
+        let connection = database_with_tokens().await;
+
+        let response = post_form(
+            &connection,
+            &[("name", "Hello"), ("access_token", "create-token")],
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        for json in [
+            json!({
+                "action": "update",
+                "url": "http://blog.example.com/hello",
+                "replace": {"content": ["Changed"]},
+            }),
+            json!({"action": "delete", "url": "http://blog.example.com/hello"}),
+        ] {
+            let response = post_json(&connection, json, "create-token").await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+            assert_eq!(json_body(response).await["error"], "insufficient_scope");
+        }
+
+        let posts = posts(&connection).await;
+        assert_eq!(posts.len(), 1);
+        assert!(posts[0].content_markdown.is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use axum::http::StatusCode;
+use chrono::NaiveDateTime;
+use entity::{page, prelude::Page};
+use pulldown_cmark::escape::escape_html;
+use sea_orm::{
+    sea_query::{Alias, Expr, JoinType},
+    ColumnTrait, ConnectionTrait, DatabaseBackend, DatabaseConnection, FromQueryResult, Order,
+    QueryFilter, QueryOrder, QuerySelect, QueryTrait, Select,
+};
+use serde::Deserialize;
+
+use crate::ErrorResponse;
+
+const MAX_SEARCH_RESULTS: u64 = 50;
+
+// Control characters that are used to delimit matches in snippets.
+// Unlike HTML tags, they survive escaping and are very unlikely to occur in posts.
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+#[derive(Deserialize)]
+pub(crate) struct SearchQuery {
+    #[serde(default)]
+    pub(crate) q: String,
+}
+
+#[derive(FromQueryResult)]
+pub(crate) struct SearchResult {
+    pub(crate) id: i32,
+    pub(crate) time: NaiveDateTime,
+    pub(crate) title: String,
+    pub(crate) url: String,
+    pub(crate) is_published: bool,
+    snippet: String,
+}
+
+impl SearchResult {
+    /// Returns the snippet as HTML, with matches highlighted using `<mark>`.
+    pub(crate) fn snippet_html(&self) -> String {
+        let mut html = String::new();
+        escape_html(&mut html, &self.snippet).unwrap();
+
+        html.replace(MATCH_START, "<mark>")
+            .replace(MATCH_END, "</mark>")
+    }
+}
+
+/// Converts a user-supplied query into an FTS5 query that matches all terms.
+/// Quoting every term prevents FTS5 operators and syntax errors.
+fn fts5_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Searches the titles and contents of `posts`, returning the best matches first.
+pub(crate) async fn search_posts(
+    connection: &DatabaseConnection,
+    posts: Select<Page>,
+    query: &str,
+) -> Result<Vec<SearchResult>, ErrorResponse> {
+    let query = query.trim();
+
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut posts = posts
+        .filter(page::Column::IsPost.eq(true))
+        .select_only()
+        .column(page::Column::Id)
+        .column(page::Column::Time)
+        .column(page::Column::Title)
+        .column(page::Column::Url)
+        .column(page::Column::IsPublished);
+
+    // The search expressions must match the structures created by
+    // the migration `m20230109_000001_create_search_index`.
+    posts = match connection.get_database_backend() {
+        DatabaseBackend::Postgres => {
+            let document =
+                r#"to_tsvector('english', "page"."title" || ' ' || "page"."content_markdown")"#;
+            let ts_query = "websearch_to_tsquery('english', $1)";
+
+            posts
+                .filter(Expr::cust_with_values(
+                    &format!("{} @@ {}", document, ts_query),
+                    [query],
+                ))
+                .column_as(
+                    Expr::cust_with_values(
+                        &format!(
+                            r#"ts_headline('english', "page"."content_markdown", {}, $2)"#,
+                            ts_query,
+                        ),
+                        [
+                            query.to_owned(),
+                            format!(
+                                "StartSel={}, StopSel={}, MaxWords=30, MinWords=15, MaxFragments=2",
+                                MATCH_START, MATCH_END,
+                            ),
+                        ],
+                    ),
+                    "snippet",
+                )
+                .order_by(
+                    Expr::cust_with_values(
+                        &format!("ts_rank({}, {})", document, ts_query),
+                        [query],
+                    ),
+                    Order::Desc,
+                )
+        }
+        DatabaseBackend::Sqlite => {
+            let fts5_query = fts5_query(query);
+
+            if fts5_query.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            QueryTrait::query(&mut posts).join(
+                JoinType::InnerJoin,
+                Alias::new("page_search"),
+                Expr::cust(r#""page_search"."rowid" = "page"."id""#),
+            );
+
+            posts
+                .filter(Expr::cust_with_values(
+                    r#""page_search" MATCH ?"#,
+                    [fts5_query],
+                ))
+                .column_as(
+                    Expr::cust_with_values(
+                        r#"snippet("page_search", 1, ?, ?, '…', 30)"#,
+                        [MATCH_START.to_string(), MATCH_END.to_string()],
+                    ),
+                    "snippet",
+                )
+                // FTS5's `rank` is more relevant the lower it is.
+                .order_by(Expr::cust(r#""page_search"."rank""#), Order::Asc)
+        }
+        DatabaseBackend::MySql => {
+            return Err((
+                StatusCode::NOT_IMPLEMENTED,
+                "search is not supported on MySQL",
+            ))
+        }
+    };
+
+    posts
+        .order_by_desc(page::Column::Time)
+        .limit(MAX_SEARCH_RESULTS)
+        .into_model::<SearchResult>()
+        .all(connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to search posts"))
+}
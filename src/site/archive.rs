@@ -0,0 +1,258 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use std::collections::BTreeMap;
+
+use askama::Template;
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use entity::page;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, FromQueryResult, QueryFilter, QueryOrder, QuerySelect,
+};
+
+use crate::{
+    site::{layout, published_pages, Layout},
+    ErrorResponse, HtmlTemplate,
+};
+
+struct Month {
+    date: NaiveDate,
+    number_of_posts: usize,
+}
+
+struct Year {
+    year: i32,
+    months: Vec<Month>,
+}
+
+#[derive(FromQueryResult)]
+struct PostTime {
+    time: NaiveDateTime,
+}
+
+#[derive(Template)]
+#[template(path = "site/archive.html")]
+struct ArchiveTemplate {
+    layout: Layout,
+    title: String,
+    years: Vec<Year>,
+}
+
+pub(super) async fn get_archive(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let post_times = published_pages()
+        .filter(page::Column::IsPost.eq(true))
+        .select_only()
+        .column(page::Column::Time)
+        .into_model::<PostTime>()
+        .all(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve posts",
+            )
+        })?;
+
+    let mut months = BTreeMap::new();
+
+    for post_time in post_times {
+        *months
+            .entry((post_time.time.year(), post_time.time.month()))
+            .or_insert(0) += 1;
+    }
+
+    let mut years: Vec<Year> = Vec::new();
+
+    // Most recent months first.
+    for ((year, month), number_of_posts) in months.into_iter().rev() {
+        let month = Month {
+            date: NaiveDate::from_ymd_opt(year, month, 1).unwrap(),
+            number_of_posts,
+        };
+
+        match years.last_mut() {
+            Some(last_year) if last_year.year == year => last_year.months.push(month),
+            _ => years.push(Year {
+                year,
+                months: vec![month],
+            }),
+        }
+    }
+
+    Ok(HtmlTemplate(ArchiveTemplate {
+        layout: layout(database_connection).await?,
+        title: "Archive".to_owned(),
+        years,
+    }))
+}
+
+#[derive(Template)]
+#[template(path = "site/archive_posts.html")]
+struct ArchivePostsTemplate {
+    layout: Layout,
+    title: String,
+    posts: Vec<page::Model>,
+}
+
+/// Renders a list of all posts published in the half-open interval `[start, end)`.
+async fn archive_posts_page(
+    database_connection: &DatabaseConnection,
+    title: String,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<Response, ErrorResponse> {
+    let posts = published_pages()
+        .filter(page::Column::IsPost.eq(true))
+        .filter(page::Column::Time.gte(start.and_hms_opt(0, 0, 0).unwrap()))
+        .filter(page::Column::Time.lt(end.and_hms_opt(0, 0, 0).unwrap()))
+        .order_by_desc(page::Column::Time)
+        .all(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve posts",
+            )
+        })?;
+
+    if posts.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "page not found"));
+    }
+
+    Ok(HtmlTemplate(ArchivePostsTemplate {
+        layout: layout(database_connection).await?,
+        title,
+        posts,
+    })
+    .into_response())
+}
+
+fn parse_year(year: &str) -> Option<i32> {
+    if year.len() == 4 && year.chars().all(|c| c.is_ascii_digit()) {
+        year.parse().ok()
+    } else {
+        None
+    }
+}
+
+pub(super) async fn get_year(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(year): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let not_found = (StatusCode::NOT_FOUND, "page not found");
+
+    let year = parse_year(&year).ok_or(not_found)?;
+
+    archive_posts_page(
+        database_connection,
+        year.to_string(),
+        NaiveDate::from_ymd_opt(year, 1, 1).ok_or(not_found)?,
+        NaiveDate::from_ymd_opt(year + 1, 1, 1).ok_or(not_found)?,
+    )
+    .await
+}
+
+pub(super) async fn get_month(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path((year, month)): Path<(String, String)>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let not_found = (StatusCode::NOT_FOUND, "page not found");
+
+    let year = parse_year(&year).ok_or(not_found)?;
+
+    if month.len() != 2 {
+        return Err(not_found);
+    }
+
+    let start =
+        NaiveDate::from_ymd_opt(year, month.parse().map_err(|_| not_found)?, 1).ok_or(not_found)?;
+
+    let end = if start.month() == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, start.month() + 1, 1)
+    }
+    .ok_or(not_found)?;
+
+    archive_posts_page(
+        database_connection,
+        start.format("%B %Y").to_string(),
+        start,
+        end,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{ActiveModelTrait, Database, Set};
+    use tower::ServiceExt;
+
+    use crate::{site::router, spam::SpamFilter};
+
+    use super::*;
+
+    async fn status(connection: &DatabaseConnection, uri: &str) -> StatusCode {
+        router()
+            .layer(Extension(SpamFilter::new(None)))
+            .layer(Extension(connection.clone()))
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn archive_does_not_hide_pages() {
+        let connection = Database::connect("sqlite::memory:").await.unwrap();
+
+        Migrator::up(&connection, None).await.unwrap();
+
+        for (url, is_post) in [("2023", false), ("post", true)] {
+            page::ActiveModel {
+                time: Set(NaiveDate::from_ymd_opt(2023, 1, 15)
+                    .unwrap()
+                    .and_hms_opt(12, 0, 0)
+                    .unwrap()),
+                title: Set(url.to_owned()),
+                url: Set(url.to_owned()),
+                content_markdown: Set(String::new()),
+                content_html: Set(String::new()),
+                is_post: Set(is_post),
+                is_published: Set(true),
+                ..Default::default()
+            }
+            .insert(&connection)
+            .await
+            .unwrap();
+        }
+
+        for uri in ["/2023", "/archive", "/archive/2023", "/archive/2023/01"] {
+            assert_eq!(status(&connection, uri).await, StatusCode::OK, "{}", uri);
+        }
+
+        for uri in [
+            "/2022",
+            "/archive/2022",
+            "/archive/2023/02",
+            "/archive/2023/1",
+        ] {
+            assert_eq!(
+                status(&connection, uri).await,
+                StatusCode::NOT_FOUND,
+                "{}",
+                uri,
+            );
+        }
+    }
+}
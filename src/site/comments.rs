@@ -0,0 +1,306 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, Path},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect},
+    Extension, Form,
+};
+use chrono::Utc;
+use entity::{comment, page, sea_orm_active_enums::CommentStatus};
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, QueryFilter, Set};
+use serde::Deserialize;
+
+use crate::{
+    admin::is_valid_site_url,
+    markdown::restricted_markdown_to_html,
+    settings,
+    site::published_pages,
+    spam::{is_spam, spam_score, SpamFilter, SpamGuardInput},
+    ErrorResponse,
+};
+
+const MAX_NAME_LENGTH: usize = 100;
+
+const MAX_CONTENT_LENGTH: usize = 10000;
+
+#[derive(Debug, Deserialize)]
+pub(super) struct CommentInput {
+    name: String,
+    #[serde(default)]
+    url: String,
+    content: String,
+    #[serde(flatten)]
+    spam_guard: SpamGuardInput,
+}
+
+/// Submits a comment on a post. Comments are only shown after an admin has approved them.
+/// Comments that look like spam go straight to the spam list.
+pub(super) async fn post_comment(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref spam_filter): Extension<SpamFilter>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(post_id): Path<String>,
+    Form(ref comment_input): Form<CommentInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let post = published_pages()
+        .filter(
+            page::Column::Id.eq(post_id
+                .parse::<i32>()
+                .map_err(|_| (StatusCode::BAD_REQUEST, "invalid post ID"))?),
+        )
+        .filter(page::Column::IsPost.eq(true))
+        .one(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve post"))?
+        .ok_or((StatusCode::NOT_FOUND, "post not found"))?;
+
+    let settings = settings(database_connection).await?;
+
+    spam_filter.check_submission(
+        &settings,
+        spam_filter.client_ip(address.ip(), &headers),
+        &comment_input.spam_guard,
+    )?;
+
+    let name = comment_input.name.trim();
+
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid name, must contain between 1 and 100 characters",
+        ));
+    }
+
+    let url = comment_input.url.trim();
+
+    if !url.is_empty() && !is_valid_site_url(url) {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid website, must start with 'http://' or 'https://'",
+        ));
+    }
+
+    let content = comment_input.content.trim();
+
+    if content.is_empty() || content.chars().count() > MAX_CONTENT_LENGTH {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid comment, must contain between 1 and 10000 characters",
+        ));
+    }
+
+    let status = if is_spam(&settings, spam_score(&[content, name, url])) {
+        CommentStatus::Spam
+    } else {
+        CommentStatus::Pending
+    };
+
+    comment::ActiveModel {
+        page_id: Set(post.id),
+        time: Set(Utc::now().naive_utc()),
+        author_name: Set(name.to_owned()),
+        author_url: Set(url.to_owned()),
+        content_markdown: Set(content.to_owned()),
+        content_html: Set(restricted_markdown_to_html(content)),
+        status: Set(status),
+        ..Default::default()
+    }
+    .insert(database_connection)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to save comment"))?;
+
+    Ok(Redirect::to(&format!(
+        "/{}?comment=submitted#comments",
+        post.url,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use axum::http::header::LOCATION;
+    use entity::{prelude::Comment, settings};
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{Database, EntityTrait};
+
+    use super::*;
+
+    async fn database_with_post(is_published: bool) -> (DatabaseConnection, page::Model) {
+        let connection = Database::connect("sqlite::memory:").await.unwrap();
+
+        Migrator::up(&connection, None).await.unwrap();
+
+        // Forms are submitted right after they are rendered.
+        let mut settings: settings::ActiveModel = settings(&connection).await.unwrap().into();
+        settings.spam_min_fill_seconds = Set(0);
+        settings.update(&connection).await.unwrap();
+
+        let post = page::ActiveModel {
+            time: Set(Utc::now().naive_utc()),
+            title: Set("A post".to_owned()),
+            url: Set("a-post".to_owned()),
+            content_markdown: Set(String::new()),
+            content_html: Set(String::new()),
+            is_post: Set(true),
+            is_published: Set(is_published),
+            ..Default::default()
+        }
+        .insert(&connection)
+        .await
+        .unwrap();
+
+        (connection, post)
+    }
+
+    async fn submit(
+        connection: &DatabaseConnection,
+        spam_filter: &SpamFilter,
+        post_id: &str,
+        fields: &[(&str, &str)],
+    ) -> Result<String, ErrorResponse> {
+        let mut fields = fields.to_vec();
+        let form_token = spam_filter.form_token();
+        fields.push(("form_token", &form_token));
+
+        let comment_input =
+            serde_urlencoded::from_str(&serde_urlencoded::to_string(fields).unwrap()).unwrap();
+
+        let response = post_comment(
+            Extension(connection.clone()),
+            Extension(spam_filter.clone()),
+            ConnectInfo(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 1234)),
+            HeaderMap::new(),
+            Path(post_id.to_owned()),
+            Form(comment_input),
+        )
+        .await?
+        .into_response();
+
+        Ok(response.headers()[LOCATION].to_str().unwrap().to_owned())
+    }
+
+    async fn comments(connection: &DatabaseConnection) -> Vec<comment::Model> {
+        Comment::find().all(connection).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn post_comment_stores_pending_comment() {
+        let (connection, post) = database_with_post(true).await;
+        let spam_filter = SpamFilter::new(None);
+
+        assert_eq!(
+            submit(
+                &connection,
+                &spam_filter,
+                &post.id.to_string(),
+                &[
+                    ("name", " Jane "),
+                    ("url", "https://jane.example"),
+                    ("content", "Nice *post* <script>alert(1)</script>"),
+                ],
+            )
+            .await,
+            Ok("/a-post?comment=submitted#comments".to_owned()),
+        );
+
+        let stored_comments = comments(&connection).await;
+        assert_eq!(stored_comments.len(), 1);
+        assert_eq!(stored_comments[0].page_id, post.id);
+        assert_eq!(stored_comments[0].author_name, "Jane");
+        assert_eq!(stored_comments[0].author_url, "https://jane.example");
+        assert_eq!(
+            stored_comments[0].content_html,
+            "<p>Nice <em>post</em> alert(1)</p>\n",
+        );
+        assert_eq!(stored_comments[0].status, CommentStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn post_comment_sends_suspicious_comment_to_spam() {
+        let (connection, post) = database_with_post(true).await;
+        let spam_filter = SpamFilter::new(None);
+
+        submit(
+            &connection,
+            &spam_filter,
+            &post.id.to_string(),
+            &[
+                ("name", "Casino"),
+                ("content", "Cheap viagra, buy now at https://a.example"),
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(comments(&connection).await[0].status, CommentStatus::Spam);
+    }
+
+    #[tokio::test]
+    async fn post_comment_validates_input() {
+        let (connection, post) = database_with_post(true).await;
+        // Without a rate limit, so that every submission is checked.
+        let spam_filter = SpamFilter::new(None);
+        let post_id = post.id.to_string();
+        let long_name = "x".repeat(MAX_NAME_LENGTH + 1);
+        let long_content = "x".repeat(MAX_CONTENT_LENGTH + 1);
+
+        let mut settings: settings::ActiveModel = settings(&connection).await.unwrap().into();
+        settings.spam_max_submissions_per_hour = Set(0);
+        settings.update(&connection).await.unwrap();
+
+        for fields in [
+            [("name", " "), ("url", ""), ("content", "Hi")],
+            [("name", &long_name), ("url", ""), ("content", "Hi")],
+            [
+                ("name", "Jane"),
+                ("url", "javascript:alert(1)"),
+                ("content", "Hi"),
+            ],
+            [("name", "Jane"), ("url", "jane.example"), ("content", "Hi")],
+            [("name", "Jane"), ("url", ""), ("content", " ")],
+            [("name", "Jane"), ("url", ""), ("content", &long_content)],
+        ] {
+            assert_eq!(
+                submit(&connection, &spam_filter, &post_id, &fields)
+                    .await
+                    .unwrap_err()
+                    .0,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "{:?}",
+                fields,
+            );
+        }
+
+        assert!(comments(&connection).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn post_comment_requires_published_post() {
+        let (connection, post) = database_with_post(false).await;
+        let spam_filter = SpamFilter::new(None);
+        let fields = [("name", "Jane"), ("content", "Hi")];
+
+        assert_eq!(
+            submit(&connection, &spam_filter, &post.id.to_string(), &fields)
+                .await
+                .unwrap_err()
+                .0,
+            StatusCode::NOT_FOUND,
+        );
+        assert_eq!(
+            submit(&connection, &spam_filter, "x", &fields)
+                .await
+                .unwrap_err()
+                .0,
+            StatusCode::BAD_REQUEST,
+        );
+
+        assert!(comments(&connection).await.is_empty());
+    }
+}
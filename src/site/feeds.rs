@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use askama::Template;
+use axum::{
+    extract::Host,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use entity::page;
+use sea_orm::{ColumnTrait, DatabaseConnection, QueryFilter, QueryOrder, QuerySelect};
+use serde::Serialize;
+
+use crate::{
+    settings,
+    site::{base_url, published_pages},
+    ErrorResponse,
+};
+
+struct Feed {
+    title: String,
+    base_url: String,
+    posts: Vec<page::Model>,
+}
+
+async fn feed(database_connection: &DatabaseConnection, host: &str) -> Result<Feed, ErrorResponse> {
+    let settings = settings(database_connection).await?;
+
+    let base_url = base_url(&settings, host);
+
+    Ok(Feed {
+        title: if settings.site_title.is_empty() {
+            base_url.clone()
+        } else {
+            settings.site_title
+        },
+        base_url,
+        posts: published_pages()
+            .filter(page::Column::IsPost.eq(true))
+            .order_by_desc(page::Column::Time)
+            .limit(settings.posts_per_page.max(1) as u64)
+            .all(database_connection)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "unable to retrieve posts",
+                )
+            })?,
+    })
+}
+
+fn render_feed(
+    template: impl Template,
+    content_type: &'static str,
+) -> Result<Response, ErrorResponse> {
+    let xml = template
+        .render()
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to render feed"))?;
+
+    Ok(([(header::CONTENT_TYPE, content_type)], xml).into_response())
+}
+
+#[derive(Template)]
+#[template(path = "site/rss.xml")]
+struct RssTemplate {
+    feed: Feed,
+}
+
+pub(super) async fn get_rss(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Host(host): Host,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    render_feed(
+        RssTemplate {
+            feed: feed(database_connection, &host).await?,
+        },
+        "application/rss+xml; charset=utf-8",
+    )
+}
+
+#[derive(Template)]
+#[template(path = "site/atom.xml")]
+struct AtomTemplate {
+    feed: Feed,
+}
+
+pub(super) async fn get_atom(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Host(host): Host,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    render_feed(
+        AtomTemplate {
+            feed: feed(database_connection, &host).await?,
+        },
+        "application/atom+xml; charset=utf-8",
+    )
+}
+
+/// See https://www.jsonfeed.org/version/1.1/
+#[derive(Serialize)]
+struct JsonFeed {
+    version: &'static str,
+    title: String,
+    home_page_url: String,
+    feed_url: String,
+    items: Vec<JsonFeedItem>,
+}
+
+#[derive(Serialize)]
+struct JsonFeedItem {
+    id: String,
+    url: String,
+    title: String,
+    content_html: String,
+    date_published: String,
+}
+
+pub(super) async fn get_json_feed(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Host(host): Host,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let feed = feed(database_connection, &host).await?;
+
+    let json_feed = JsonFeed {
+        version: "https://jsonfeed.org/version/1.1",
+        title: feed.title,
+        home_page_url: format!("{}/", feed.base_url),
+        feed_url: format!("{}/feed.json", feed.base_url),
+        items: feed
+            .posts
+            .into_iter()
+            .map(|post| JsonFeedItem {
+                id: format!("{}/{}", feed.base_url, post.url),
+                url: format!("{}/{}", feed.base_url, post.url),
+                title: post.title,
+                content_html: post.content_html,
+                date_published: post.time.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            })
+            .collect(),
+    };
+
+    let json = serde_json::to_string(&json_feed)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to render feed"))?;
+
+    Ok(([(header::CONTENT_TYPE, "application/feed+json")], json))
+}
@@ -0,0 +1,59 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use axum::{
+    extract::Path,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension,
+};
+use entity::{file, prelude::File};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+
+use crate::ErrorResponse;
+
+/// Returns whether files of type `mime_type` can safely be displayed in the browser.
+/// Other types, such as HTML and SVG, could run scripts on the site's origin,
+/// so they are served as downloads instead.
+fn is_passive_mime_type(mime_type: &str) -> bool {
+    let mime_type = mime_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    (mime_type.starts_with("image/") && mime_type != "image/svg+xml")
+        || mime_type.starts_with("audio/")
+        || mime_type.starts_with("video/")
+        || mime_type == "application/pdf"
+        || mime_type == "text/plain"
+}
+
+pub(super) async fn get_file(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let file = File::find()
+        .filter(file::Column::Name.eq(name))
+        .one(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve file"))?
+        .ok_or((StatusCode::NOT_FOUND, "file not found"))?;
+
+    // File names contain only characters that are safe to use in the header.
+    let content_disposition = if is_passive_mime_type(&file.mime_type) {
+        format!("inline; filename=\"{}\"", file.name)
+    } else {
+        format!("attachment; filename=\"{}\"", file.name)
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, file.mime_type),
+            (header::CONTENT_DISPOSITION, content_disposition),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_owned()),
+        ],
+        file.content,
+    ))
+}
@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+mod archive;
+mod comments;
+mod feeds;
+mod files;
+mod pages;
+mod posts;
+mod preview;
+mod search;
+mod sitemap;
+mod tags;
+
+use axum::{
+    http::StatusCode,
+    routing::{get, post},
+    Router,
+};
+use chrono::Utc;
+use entity::{page, prelude::Page};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Select};
+
+use crate::{settings, ErrorResponse};
+
+pub(super) fn router() -> Router {
+    Router::new()
+        .route("/", get(posts::get_posts))
+        .route("/page/:page_number", get(posts::get_posts_page))
+        .route("/tag/:name", get(tags::get_tag))
+        .route("/tag/:name/page/:page_number", get(tags::get_tag_page))
+        .route("/feed.xml", get(feeds::get_rss))
+        .route("/atom.xml", get(feeds::get_atom))
+        .route("/feed.json", get(feeds::get_json_feed))
+        .route("/sitemap.xml", get(sitemap::get_sitemap))
+        .route("/robots.txt", get(sitemap::get_robots_txt))
+        .route("/files/:name", get(files::get_file))
+        .route("/preview/:token", get(preview::get_preview))
+        .route("/archive", get(archive::get_archive))
+        .route("/archive/:year", get(archive::get_year))
+        .route("/archive/:year/:month", get(archive::get_month))
+        .route("/search", get(search::get_search))
+        .route("/comments/:post_id", post(comments::post_comment))
+        .route("/:url", get(pages::get_page))
+}
+
+/// Data shared by all public pages, used by `site/base.html`.
+struct Layout {
+    settings: settings::Model,
+    menu_pages: Vec<page::Model>,
+}
+
+async fn layout(connection: &DatabaseConnection) -> Result<Layout, ErrorResponse> {
+    Ok(Layout {
+        settings: settings(connection).await?,
+        menu_pages: published_pages()
+            .filter(page::Column::IsPost.eq(false))
+            .filter(page::Column::MenuOrder.is_not_null())
+            .order_by_asc(page::Column::MenuOrder)
+            .all(connection)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "unable to retrieve menu pages",
+                )
+            })?,
+    })
+}
+
+/// Returns all pages that are visible to the public. Published pages
+/// whose time lies in the future are scheduled, and remain hidden until then.
+pub(crate) fn published_pages() -> Select<Page> {
+    Page::find()
+        .filter(page::Column::IsPublished.eq(true))
+        .filter(page::Column::Time.lte(Utc::now().naive_utc()))
+}
+
+/// Returns the absolute URL of the site, without a trailing slash.
+/// If no site URL has been configured, it is derived from the request's host.
+pub(crate) fn base_url(settings: &settings::Model, host: &str) -> String {
+    if settings.site_url.is_empty() {
+        format!("http://{}", host)
+    } else {
+        settings.site_url.clone()
+    }
+}
@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use askama::Template;
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use entity::{
+    comment, page,
+    prelude::{Comment, Tag, Webmention},
+    sea_orm_active_enums::CommentStatus,
+    tag, webmention,
+};
+use sea_orm::{ColumnTrait, DatabaseConnection, ModelTrait, QueryFilter, QueryOrder};
+use serde::Deserialize;
+
+use crate::{
+    site::{layout, published_pages, Layout},
+    spam::SpamFilter,
+    ErrorResponse, HtmlTemplate,
+};
+
+#[derive(Template)]
+#[template(path = "site/page.html")]
+struct PageTemplate {
+    layout: Layout,
+    title: String,
+    page: page::Model,
+    tags: Vec<tag::Model>,
+    mentions: Vec<webmention::Model>,
+    comments: Vec<comment::Model>,
+    comment_form_token: Option<String>,
+    is_comment_submitted: bool,
+}
+
+#[derive(Deserialize)]
+pub(super) struct PageQuery {
+    /// Set after a comment has been submitted, to let the commenter know
+    /// that it will not be shown until it has been approved.
+    #[serde(default)]
+    comment: String,
+}
+
+pub(super) async fn get_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Extension(ref spam_filter): Extension<SpamFilter>,
+    Path(url): Path<String>,
+    Query(page_query): Query<PageQuery>,
+) -> Result<Response, ErrorResponse> {
+    // URLs are not guaranteed to be unique. If multiple published pages
+    // share the same URL, the most recent one wins.
+    let page = published_pages()
+        .filter(page::Column::Url.eq(url.as_str()))
+        .order_by_desc(page::Column::Time)
+        .one(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve page"))?
+        .ok_or((StatusCode::NOT_FOUND, "page not found"))?;
+
+    page_response(
+        database_connection,
+        page,
+        Some(spam_filter),
+        page_query.comment == "submitted",
+    )
+    .await
+}
+
+/// Renders `page` the way it appears on the public site.
+/// The comment form is shown only if `spam_filter` is given,
+/// which it isn't when the page is being previewed.
+pub(super) async fn page_response(
+    database_connection: &DatabaseConnection,
+    page: page::Model,
+    spam_filter: Option<&SpamFilter>,
+    is_comment_submitted: bool,
+) -> Result<Response, ErrorResponse> {
+    let tags = page
+        .find_related(Tag)
+        .order_by_asc(tag::Column::Name)
+        .all(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve tags"))?;
+
+    let mentions = page
+        .find_related(Webmention)
+        .filter(webmention::Column::IsApproved.eq(true))
+        .order_by_asc(webmention::Column::Received)
+        .all(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve mentions",
+            )
+        })?;
+
+    let comments = page
+        .find_related(Comment)
+        .filter(comment::Column::Status.eq(CommentStatus::Approved))
+        .order_by_asc(comment::Column::Time)
+        .all(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve comments",
+            )
+        })?;
+
+    Ok(HtmlTemplate(PageTemplate {
+        layout: layout(database_connection).await?,
+        title: page.title.clone(),
+        comment_form_token: spam_filter
+            .filter(|_| page.is_post)
+            .map(SpamFilter::form_token),
+        page,
+        tags,
+        mentions,
+        comments,
+        is_comment_submitted,
+    })
+    .into_response())
+}
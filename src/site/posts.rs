@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use askama::Template;
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension};
+use entity::{page, prelude::Page};
+use sea_orm::{ColumnTrait, DatabaseConnection, PaginatorTrait, QueryFilter, QueryOrder, Select};
+
+use crate::{
+    site::{layout, published_pages, Layout},
+    ErrorResponse, HtmlTemplate,
+};
+
+#[derive(Template)]
+#[template(path = "site/posts.html")]
+struct PostsTemplate {
+    layout: Layout,
+    title: String,
+    heading: String,
+    posts: Vec<page::Model>,
+    newer_posts_url: Option<String>,
+    older_posts_url: Option<String>,
+}
+
+pub(super) fn parse_page_number(page_number: &str) -> Result<u64, ErrorResponse> {
+    match page_number.parse() {
+        Ok(page_number) if page_number > 0 => Ok(page_number),
+        _ => Err((StatusCode::NOT_FOUND, "page not found")),
+    }
+}
+
+/// Renders page `page_number` of a paginated list of posts,
+/// where `page_url` returns the URL of the page with the given number.
+pub(super) async fn posts_page(
+    database_connection: &DatabaseConnection,
+    posts: Select<Page>,
+    heading: String,
+    page_number: u64,
+    page_url: impl Fn(u64) -> String,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let layout = layout(database_connection).await?;
+
+    let paginator = posts
+        .filter(page::Column::IsPost.eq(true))
+        .order_by_desc(page::Column::Time)
+        .paginate(
+            database_connection,
+            layout.settings.posts_per_page.max(1) as u64,
+        );
+
+    let number_of_pages = paginator.num_pages().await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to retrieve posts",
+        )
+    })?;
+
+    // The first page always exists, even if there are no posts to show on it.
+    if page_number > number_of_pages.max(1) {
+        return Err((StatusCode::NOT_FOUND, "page not found"));
+    }
+
+    let posts = paginator.fetch_page(page_number - 1).await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to retrieve posts",
+        )
+    })?;
+
+    Ok(HtmlTemplate(PostsTemplate {
+        layout,
+        title: if page_number == 1 {
+            heading.clone()
+        } else if heading.is_empty() {
+            format!("Page {}", page_number)
+        } else {
+            format!("{}, page {}", heading, page_number)
+        },
+        heading,
+        posts,
+        newer_posts_url: if page_number > 1 {
+            Some(page_url(page_number - 1))
+        } else {
+            None
+        },
+        older_posts_url: if page_number < number_of_pages {
+            Some(page_url(page_number + 1))
+        } else {
+            None
+        },
+    }))
+}
+
+fn posts_page_url(page_number: u64) -> String {
+    if page_number == 1 {
+        "/".to_owned()
+    } else {
+        format!("/page/{}", page_number)
+    }
+}
+
+pub(super) async fn get_posts(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    posts_page(
+        database_connection,
+        published_pages(),
+        String::new(),
+        1,
+        posts_page_url,
+    )
+    .await
+}
+
+pub(super) async fn get_posts_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(page_number): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    posts_page(
+        database_connection,
+        published_pages(),
+        String::new(),
+        parse_page_number(&page_number)?,
+        posts_page_url,
+    )
+    .await
+}
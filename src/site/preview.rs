@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use axum::{
+    extract::Path,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension,
+};
+use chrono::Utc;
+use entity::{
+    prelude::{Page, PreviewLink},
+    preview_link,
+};
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter};
+
+use crate::{site::pages::page_response, ErrorResponse};
+
+/// Renders a page, which need not be published, for anyone who knows
+/// the secret token of one of its preview links.
+pub(super) async fn get_preview(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(token): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let (_, page) = PreviewLink::find()
+        .filter(preview_link::Column::Token.eq(token))
+        .filter(
+            Condition::any()
+                .add(preview_link::Column::Expires.is_null())
+                .add(preview_link::Column::Expires.gt(Utc::now().naive_utc())),
+        )
+        .find_also_related(Page)
+        .one(database_connection)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to retrieve preview link",
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "preview link not found or expired"))?;
+
+    let page = page.ok_or((StatusCode::NOT_FOUND, "page not found"))?;
+
+    Ok((
+        [
+            // Previews must not show up in search engines.
+            (header::HeaderName::from_static("x-robots-tag"), "noindex"),
+            // The content hasn't been reviewed by an editor yet, but is shown on
+            // the site's origin to users who may be logged in. Sandboxing gives it
+            // a unique origin, and prevents it from running scripts.
+            (header::CONTENT_SECURITY_POLICY, "sandbox"),
+        ],
+        page_response(database_connection, page, None, false).await?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::page;
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{ActiveModelTrait, Database, Set};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn preview_is_sandboxed() {
+        let connection = Database::connect("sqlite::memory:").await.unwrap();
+
+        Migrator::up(&connection, None).await.unwrap();
+
+        let page = page::ActiveModel {
+            time: Set(Utc::now().naive_utc()),
+            title: Set("Draft".to_owned()),
+            url: Set("draft".to_owned()),
+            content_markdown: Set(String::new()),
+            content_html: Set("<script>alert(1)</script>".to_owned()),
+            is_post: Set(true),
+            is_published: Set(false),
+            ..Default::default()
+        }
+        .insert(&connection)
+        .await
+        .unwrap();
+
+        preview_link::ActiveModel {
+            page_id: Set(page.id),
+            token: Set("secret".to_owned()),
+            created: Set(Utc::now().naive_utc()),
+            expires: Set(None),
+            ..Default::default()
+        }
+        .insert(&connection)
+        .await
+        .unwrap();
+
+        let response = get_preview(Extension(connection), Path("secret".to_owned()))
+            .await
+            .unwrap()
+            .into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_SECURITY_POLICY],
+            "sandbox"
+        );
+        assert_eq!(response.headers()["x-robots-tag"], "noindex");
+    }
+}
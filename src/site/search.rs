@@ -0,0 +1,33 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use askama::Template;
+use axum::{extract::Query, response::IntoResponse, Extension};
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    search::{search_posts, SearchQuery, SearchResult},
+    site::{layout, published_pages, Layout},
+    ErrorResponse, HtmlTemplate,
+};
+
+#[derive(Template)]
+#[template(path = "site/search.html")]
+struct SearchTemplate {
+    layout: Layout,
+    title: String,
+    query: String,
+    results: Vec<SearchResult>,
+}
+
+pub(super) async fn get_search(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Query(SearchQuery { q: query }): Query<SearchQuery>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    Ok(HtmlTemplate(SearchTemplate {
+        layout: layout(database_connection).await?,
+        title: "Search".to_owned(),
+        results: search_posts(database_connection, published_pages(), &query).await?,
+        query,
+    }))
+}
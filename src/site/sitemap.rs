@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use askama::Template;
+use axum::{
+    extract::Host,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension,
+};
+use entity::page;
+use sea_orm::{DatabaseConnection, QueryOrder};
+
+use crate::{
+    settings,
+    site::{base_url, published_pages},
+    ErrorResponse,
+};
+
+#[derive(Template)]
+#[template(path = "site/sitemap.xml")]
+struct SitemapTemplate {
+    base_url: String,
+    pages: Vec<page::Model>,
+}
+
+pub(super) async fn get_sitemap(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Host(host): Host,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let xml = SitemapTemplate {
+        base_url: base_url(&settings(database_connection).await?, &host),
+        pages: published_pages()
+            .order_by_desc(page::Column::Time)
+            .all(database_connection)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "unable to retrieve pages",
+                )
+            })?,
+    }
+    .render()
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to render sitemap",
+        )
+    })?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        xml,
+    ))
+}
+
+pub(super) async fn get_robots_txt(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Host(host): Host,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let settings = settings(database_connection).await?;
+
+    let mut robots_txt = settings.robots_txt.trim_end().to_owned();
+
+    if !robots_txt.is_empty() {
+        robots_txt.push_str("\n\n");
+    }
+
+    robots_txt.push_str(&format!(
+        "Sitemap: {}/sitemap.xml\n",
+        base_url(&settings, &host),
+    ));
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        robots_txt,
+    ))
+}
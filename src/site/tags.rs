@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension};
+use entity::{page, page_tag, prelude::Tag, tag};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, JoinType, QueryFilter, QuerySelect, RelationTrait,
+};
+
+use crate::{
+    site::{
+        posts::{parse_page_number, posts_page},
+        published_pages,
+    },
+    ErrorResponse,
+};
+
+async fn tag_posts_page(
+    database_connection: &DatabaseConnection,
+    name: String,
+    page_number: u64,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let tag = Tag::find()
+        .filter(tag::Column::Name.eq(name))
+        .one(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve tag"))?
+        .ok_or((StatusCode::NOT_FOUND, "tag not found"))?;
+
+    let posts = published_pages()
+        .join(JoinType::InnerJoin, page::Relation::PageTag.def())
+        .filter(page_tag::Column::TagId.eq(tag.id));
+
+    posts_page(
+        database_connection,
+        posts,
+        format!("Posts tagged \u{201C}{}\u{201D}", tag.name),
+        page_number,
+        move |page_number| {
+            if page_number == 1 {
+                format!("/tag/{}", tag.name)
+            } else {
+                format!("/tag/{}/page/{}", tag.name, page_number)
+            }
+        },
+    )
+    .await
+}
+
+pub(super) async fn get_tag(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    tag_posts_page(database_connection, name, 1).await
+}
+
+pub(super) async fn get_tag_page(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Path((name, page_number)): Path<(String, String)>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    tag_posts_page(database_connection, name, parse_page_number(&page_number)?).await
+}
@@ -0,0 +1,524 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::http::{header::HeaderName, HeaderMap, StatusCode};
+use chrono::Utc;
+use entity::settings;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{random_token, ErrorResponse};
+
+/// Words that rarely appear in genuine comments on a personal site.
+const SPAM_WORDS: [&str; 16] = [
+    "viagra",
+    "cialis",
+    "casino",
+    "poker",
+    "betting",
+    "payday",
+    "loan",
+    "crypto",
+    "forex",
+    "bitcoin",
+    "backlink",
+    "seo service",
+    "replica",
+    "weight loss",
+    "click here",
+    "buy now",
+];
+
+/// Forms rendered longer ago than this must be reloaded before they can be submitted,
+/// so that a token can't be harvested once and reused indefinitely.
+const MAX_FORM_AGE_SECONDS: i64 = 6 * 60 * 60;
+
+/// The hidden fields that every public form must include.
+/// Forms that embed this struct should do so using `#[serde(flatten)]`.
+#[derive(Debug, Deserialize)]
+pub(crate) struct SpamGuardInput {
+    /// Issued by `SpamFilter::form_token` when the form was rendered.
+    #[serde(default)]
+    form_token: String,
+    /// Honeypot field, hidden from humans. Bots tend to fill in every field they find.
+    #[serde(default)]
+    homepage: String,
+}
+
+/// Protects public forms against automated submissions.
+/// A single instance is shared between all requests.
+#[derive(Clone)]
+pub(crate) struct SpamFilter {
+    /// Used to sign form tokens. Generated on startup, which means that
+    /// forms rendered before a restart have to be reloaded.
+    secret: String,
+    /// Times of recent submissions, by client IP address.
+    submissions: Arc<Mutex<HashMap<IpAddr, Vec<Instant>>>>,
+    /// The header containing the client IP address, if the site is behind a reverse proxy.
+    client_ip_header: Option<HeaderName>,
+}
+
+impl SpamFilter {
+    pub(crate) fn new(client_ip_header: Option<HeaderName>) -> Self {
+        SpamFilter {
+            secret: random_token(),
+            submissions: Default::default(),
+            client_ip_header,
+        }
+    }
+
+    /// Returns the IP address of the client that sent a request over a connection
+    /// from `connection_address`. Behind a reverse proxy, all connections come from
+    /// the proxy, so the address is taken from the configured header instead.
+    /// If the header contains a list of addresses, as `X-Forwarded-For` does,
+    /// the last one is used, because it was added by the proxy itself,
+    /// while the others could have been sent by the client.
+    pub(crate) fn client_ip(&self, connection_address: IpAddr, headers: &HeaderMap) -> IpAddr {
+        self.client_ip_header
+            .as_ref()
+            .and_then(|header| headers.get(header))
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|address| address.trim().parse().ok())
+            .unwrap_or(connection_address)
+    }
+
+    fn signature(&self, timestamp: i64) -> String {
+        format!(
+            "{:x}",
+            Sha256::digest(format!("{}:{}", timestamp, self.secret).as_bytes()),
+        )
+    }
+
+    /// Returns a token recording the current time, to be included in a form.
+    pub(crate) fn form_token(&self) -> String {
+        let timestamp = Utc::now().timestamp();
+
+        format!("{}.{}", timestamp, self.signature(timestamp))
+    }
+
+    /// Returns the time the form containing `form_token` was rendered,
+    /// or `None` if the token was not issued by this filter.
+    fn form_token_timestamp(&self, form_token: &str) -> Option<i64> {
+        let (timestamp, signature) = form_token.split_once('.')?;
+
+        let timestamp = timestamp.parse().ok()?;
+
+        (signature == self.signature(timestamp)).then_some(timestamp)
+    }
+
+    /// Records a submission from `ip_address`, and returns whether
+    /// fewer than `max_submissions` were made in the past hour.
+    fn record_submission(&self, ip_address: IpAddr, max_submissions: usize) -> bool {
+        let mut submissions = self.submissions.lock().unwrap();
+
+        let now = Instant::now();
+        let hour = Duration::from_secs(60 * 60);
+
+        // Forget old submissions so memory use stays bounded.
+        submissions.retain(|_, times| {
+            times.retain(|time| now.duration_since(*time) < hour);
+            !times.is_empty()
+        });
+
+        let times = submissions.entry(ip_address).or_default();
+
+        if times.len() >= max_submissions {
+            return false;
+        }
+
+        times.push(now);
+
+        true
+    }
+
+    /// Rejects submissions that fill in the honeypot field, arrive too soon or too long
+    /// after the form was rendered, or exceed the per-IP rate limit from the settings.
+    pub(crate) fn check_submission(
+        &self,
+        settings: &settings::Model,
+        ip_address: IpAddr,
+        spam_guard_input: &SpamGuardInput,
+    ) -> Result<(), ErrorResponse> {
+        if !spam_guard_input.homepage.is_empty() {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "submission rejected as spam",
+            ));
+        }
+
+        let invalid_form_error = (
+            StatusCode::BAD_REQUEST,
+            "invalid or expired form, please reload the page and try again",
+        );
+
+        let timestamp = self
+            .form_token_timestamp(&spam_guard_input.form_token)
+            .ok_or(invalid_form_error)?;
+
+        let form_age = Utc::now().timestamp() - timestamp;
+
+        if form_age > MAX_FORM_AGE_SECONDS {
+            return Err(invalid_form_error);
+        }
+
+        if form_age < i64::from(settings.spam_min_fill_seconds) {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "form submitted too quickly, please wait a moment and try again",
+            ));
+        }
+
+        // A limit of zero disables rate limiting.
+        if settings.spam_max_submissions_per_hour > 0
+            && !self.record_submission(ip_address, settings.spam_max_submissions_per_hour as usize)
+        {
+            return Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                "too many submissions, please try again later",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Estimates how likely it is that a submission is spam, based only on its text.
+/// Higher scores are more suspicious. `fields` are the submitted values,
+/// with the main text (e.g. the comment body) first.
+pub(crate) fn spam_score(fields: &[&str]) -> i32 {
+    let mut score = 0;
+
+    for (i, field) in fields.iter().enumerate() {
+        let lowercase = field.to_lowercase();
+
+        let links = lowercase.matches("http://").count()
+            + lowercase.matches("https://").count()
+            + lowercase.matches("www.").count();
+
+        if i == 0 {
+            // A single link in the body is normal, many are not.
+            score += 2 * (links.saturating_sub(1) as i32);
+
+            // Link markup from forum software, which Markdown doesn't use.
+            if lowercase.contains("[url=") || lowercase.contains("[/url]") {
+                score += 3;
+            }
+
+            // Short bodies that consist mostly of a link.
+            if links > 0 && field.chars().count() < 40 {
+                score += 2;
+            }
+
+            let letters = field.chars().filter(|c| c.is_alphabetic()).count();
+            let uppercase_letters = field.chars().filter(|c| c.is_uppercase()).count();
+
+            if letters >= 20 && uppercase_letters * 2 > letters {
+                score += 1;
+            }
+        } else if !lowercase.starts_with("http") && links > 0 {
+            // Links in fields that aren't meant to hold one, such as a name.
+            score += 3;
+        }
+
+        score += SPAM_WORDS
+            .iter()
+            .filter(|word| lowercase.contains(*word))
+            .count() as i32
+            * 2;
+    }
+
+    score
+}
+
+/// Returns whether a submission with `spam_score` should be treated as spam,
+/// according to the threshold from the settings. A threshold of zero disables scoring.
+pub(crate) fn is_spam(settings: &settings::Model, spam_score: i32) -> bool {
+    settings.spam_score_threshold > 0 && spam_score >= settings.spam_score_threshold
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    fn test_settings(
+        spam_min_fill_seconds: i32,
+        spam_max_submissions_per_hour: i32,
+        spam_score_threshold: i32,
+    ) -> settings::Model {
+        settings::Model {
+            id: 1,
+            header_markdown: String::new(),
+            header_html: String::new(),
+            footer_markdown: String::new(),
+            footer_html: String::new(),
+            css: String::new(),
+            javascript: String::new(),
+            posts_per_page: 10,
+            site_title: String::new(),
+            site_url: String::new(),
+            robots_txt: String::new(),
+            spam_min_fill_seconds,
+            spam_max_submissions_per_hour,
+            spam_score_threshold,
+            actor_private_key: String::new(),
+        }
+    }
+
+    fn ip_address(last_octet: u8) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::new(192, 0, 2, last_octet))
+    }
+
+    /// Returns a token for a form that was rendered `age_seconds` ago.
+    fn form_token(spam_filter: &SpamFilter, age_seconds: i64) -> String {
+        let timestamp = Utc::now().timestamp() - age_seconds;
+
+        format!("{}.{}", timestamp, spam_filter.signature(timestamp))
+    }
+
+    fn spam_guard_input(form_token: String, homepage: &str) -> SpamGuardInput {
+        SpamGuardInput {
+            form_token,
+            homepage: homepage.to_owned(),
+        }
+    }
+
+    #[test]
+    fn accepts_valid_submission() {
+        let spam_filter = SpamFilter::new(None);
+
+        assert_eq!(
+            spam_filter.check_submission(
+                &test_settings(3, 10, 5),
+                ip_address(1),
+                &spam_guard_input(form_token(&spam_filter, 10), ""),
+            ),
+            Ok(()),
+        );
+    }
+
+    #[test]
+    fn rejects_filled_honeypot() {
+        let spam_filter = SpamFilter::new(None);
+
+        let result = spam_filter.check_submission(
+            &test_settings(3, 10, 5),
+            ip_address(1),
+            &spam_guard_input(form_token(&spam_filter, 10), "https://example.com"),
+        );
+
+        assert_eq!(result.unwrap_err().0, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn rejects_submission_before_fill_time() {
+        let spam_filter = SpamFilter::new(None);
+
+        let result = spam_filter.check_submission(
+            &test_settings(3, 10, 5),
+            ip_address(1),
+            &spam_guard_input(spam_filter.form_token(), ""),
+        );
+
+        assert_eq!(result.unwrap_err().0, StatusCode::UNPROCESSABLE_ENTITY);
+
+        // A fill time of zero disables the check.
+        assert_eq!(
+            spam_filter.check_submission(
+                &test_settings(0, 10, 5),
+                ip_address(1),
+                &spam_guard_input(spam_filter.form_token(), ""),
+            ),
+            Ok(()),
+        );
+    }
+
+    #[test]
+    fn rejects_expired_token() {
+        let spam_filter = SpamFilter::new(None);
+
+        let result = spam_filter.check_submission(
+            &test_settings(3, 10, 5),
+            ip_address(1),
+            &spam_guard_input(form_token(&spam_filter, MAX_FORM_AGE_SECONDS + 60), ""),
+        );
+
+        assert_eq!(result.unwrap_err().0, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn rejects_token_with_bad_signature() {
+        let spam_filter = SpamFilter::new(None);
+
+        let timestamp = Utc::now().timestamp() - 10;
+
+        for form_token in [
+            format!("{}.{}", timestamp, "0".repeat(64)),
+            // Issued by a different filter, e.g. before a restart.
+            form_token(&SpamFilter::new(None), 10),
+            // The signature of a different time.
+            format!("{}.{}", timestamp, spam_filter.signature(timestamp - 1)),
+            String::new(),
+        ] {
+            let result = spam_filter.check_submission(
+                &test_settings(3, 10, 5),
+                ip_address(1),
+                &spam_guard_input(form_token, ""),
+            );
+
+            assert_eq!(result.unwrap_err().0, StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[test]
+    fn record_submission_enforces_limit() {
+        let spam_filter = SpamFilter::new(None);
+
+        assert!(spam_filter.record_submission(ip_address(1), 2));
+        assert!(spam_filter.record_submission(ip_address(1), 2));
+        assert!(!spam_filter.record_submission(ip_address(1), 2));
+
+        // Other clients are counted separately.
+        assert!(spam_filter.record_submission(ip_address(2), 2));
+    }
+
+    #[test]
+    fn record_submission_forgets_submissions_after_window() {
+        let spam_filter = SpamFilter::new(None);
+
+        let over_an_hour_ago = Instant::now()
+            .checked_sub(Duration::from_secs(61 * 60))
+            .unwrap();
+
+        spam_filter
+            .submissions
+            .lock()
+            .unwrap()
+            .insert(ip_address(1), vec![over_an_hour_ago, over_an_hour_ago]);
+
+        assert!(spam_filter.record_submission(ip_address(1), 2));
+        assert_eq!(
+            spam_filter.submissions.lock().unwrap()[&ip_address(1)].len(),
+            1
+        );
+    }
+
+    #[test]
+    fn check_submission_applies_rate_limit() {
+        let spam_filter = SpamFilter::new(None);
+        let settings = test_settings(0, 1, 5);
+
+        let check = || {
+            spam_filter.check_submission(
+                &settings,
+                ip_address(1),
+                &spam_guard_input(spam_filter.form_token(), ""),
+            )
+        };
+
+        assert_eq!(check(), Ok(()));
+        assert_eq!(check().unwrap_err().0, StatusCode::TOO_MANY_REQUESTS);
+
+        // A limit of zero disables rate limiting.
+        let settings = test_settings(0, 0, 5);
+
+        for _ in 0..5 {
+            assert_eq!(
+                spam_filter.check_submission(
+                    &settings,
+                    ip_address(1),
+                    &spam_guard_input(spam_filter.form_token(), ""),
+                ),
+                Ok(()),
+            );
+        }
+    }
+
+    #[test]
+    fn client_ip_uses_configured_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "198.51.100.1, 198.51.100.2".parse().unwrap(),
+        );
+
+        assert_eq!(
+            SpamFilter::new(None).client_ip(ip_address(1), &headers),
+            ip_address(1),
+        );
+
+        let spam_filter = SpamFilter::new(Some(HeaderName::from_static("x-forwarded-for")));
+
+        assert_eq!(
+            spam_filter.client_ip(ip_address(1), &headers),
+            "198.51.100.2".parse::<IpAddr>().unwrap(),
+        );
+        assert_eq!(
+            spam_filter.client_ip(ip_address(1), &HeaderMap::new()),
+            ip_address(1),
+        );
+    }
+
+    #[test]
+    fn spam_score_of_genuine_comment() {
+        assert_eq!(
+            spam_score(&[
+                "Thanks for the write-up, see also https://example.com/related for more.",
+                "Jane",
+                "https://example.org",
+            ]),
+            0,
+        );
+    }
+
+    #[test]
+    fn spam_score_of_suspicious_comments() {
+        // Additional links in the body.
+        assert_eq!(
+            spam_score(&["A long enough comment with https://a.example and https://b.example"]),
+            2,
+        );
+        // Forum link markup.
+        assert_eq!(spam_score(&["Some text [url=x]y[/url] and more text"]), 3);
+        // A short body that is mostly a link.
+        assert_eq!(spam_score(&["https://a.example"]), 2);
+        // Shouting.
+        assert_eq!(spam_score(&["THIS IS A VERY LOUD COMMENT INDEED"]), 1);
+        // A link in the name field.
+        assert_eq!(
+            spam_score(&["Nice post, thank you", "visit www.example.com"]),
+            3
+        );
+        // Spam words, in any field.
+        assert_eq!(
+            spam_score(&["Cheap VIAGRA and casino bonuses", "payday"]),
+            6,
+        );
+    }
+
+    #[test]
+    fn is_spam_uses_threshold() {
+        let settings = test_settings(3, 10, 5);
+
+        assert!(!is_spam(&settings, 4));
+        assert!(is_spam(&settings, 5));
+        assert!(is_spam(&settings, 20));
+    }
+
+    #[test]
+    fn is_spam_disabled_with_zero_threshold() {
+        let settings = test_settings(3, 10, 0);
+
+        assert!(!is_spam(&settings, 0));
+        assert!(!is_spam(&settings, 100));
+    }
+}
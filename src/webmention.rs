@@ -0,0 +1,827 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+//! Webmention (https://www.w3.org/TR/webmention/) support. Mentions of posts
+//! are received, verified in the background, and shown once an admin approves them.
+//! When a post is published, the pages it links to are notified.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    http::{header::LINK, StatusCode},
+    response::IntoResponse,
+    routing::post,
+    Extension, Form, Router,
+};
+use chrono::Utc;
+use entity::{page, prelude::Webmention, webmention};
+use hyper::client::connect::dns::Name;
+use regex::Regex;
+use reqwest::{
+    dns::{Addrs, Resolve, Resolving},
+    redirect::{Attempt, Policy},
+    Client, RequestBuilder, Response, Url,
+};
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, ModelTrait, QueryFilter, Set};
+use serde::Deserialize;
+use tokio::sync::Semaphore;
+
+use crate::{admin::is_valid_site_url, settings, site::published_pages, ErrorResponse};
+
+pub(super) const WEBMENTION_URL: &str = "/webmention";
+
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+
+/// Pages larger than this are not searched for links or endpoints.
+const MAX_RESPONSE_SIZE: usize = 1024 * 1024;
+
+const MAX_TITLE_LENGTH: usize = 200;
+
+const MAX_REDIRECTS: usize = 5;
+
+/// Received mentions are verified in the background, but only this many at a time,
+/// so that a flood of mentions can't tie up the server with outgoing requests.
+const MAX_CONCURRENT_VERIFICATIONS: usize = 10;
+
+static VERIFICATIONS: Semaphore = Semaphore::const_new(MAX_CONCURRENT_VERIFICATIONS);
+
+pub(super) fn router() -> Router {
+    Router::new().route(WEBMENTION_URL, post(post_webmention))
+}
+
+/// Returns whether `ip_address` is reachable from the public internet.
+fn is_public_address(ip_address: IpAddr) -> bool {
+    match ip_address {
+        IpAddr::V4(ip_address) => {
+            let octets = ip_address.octets();
+
+            !(ip_address.is_unspecified()
+                || ip_address.is_loopback()
+                || ip_address.is_private()
+                || ip_address.is_link_local()
+                || ip_address.is_broadcast()
+                || ip_address.is_documentation()
+                // "This network" (0.0.0.0/8) and shared address space (100.64.0.0/10).
+                || octets[0] == 0
+                || (octets[0] == 100 && (octets[1] & 0b1100_0000) == 64))
+        }
+        IpAddr::V6(ip_address) => match ip_address.to_ipv4_mapped() {
+            Some(ip_address) => is_public_address(IpAddr::V4(ip_address)),
+            None => {
+                let first_segment = ip_address.segments()[0];
+
+                !(ip_address.is_unspecified()
+                    || ip_address.is_loopback()
+                    // Unique local (fc00::/7) and link-local (fe80::/10) addresses.
+                    || (first_segment & 0xfe00) == 0xfc00
+                    || (first_segment & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Returns whether requests to `url` are allowed. Host names are checked
+/// when they are resolved, but IP addresses in URLs are never resolved.
+fn is_public_url(url: &Url) -> bool {
+    (url.scheme() == "http" || url.scheme() == "https")
+        && url.host_str().map_or(false, |host| {
+            // IPv6 addresses are enclosed in brackets.
+            host.trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse()
+                .map_or(true, is_public_address)
+        })
+}
+
+/// Resolves host names like the system resolver does, but only to public addresses.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|address| is_public_address(address.ip()))
+                .collect();
+
+            if addresses.is_empty() {
+                return Err("host has no public address".into());
+            }
+
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn redirect_policy(attempt: Attempt) -> reqwest::redirect::Action {
+    if attempt.previous().len() >= MAX_REDIRECTS {
+        attempt.error("too many redirects")
+    } else if !is_public_url(attempt.url()) {
+        attempt.error("redirect to a non-public address")
+    } else {
+        attempt.follow()
+    }
+}
+
+/// Returns a client for requests to URLs supplied by other sites, which refuses
+/// to connect to loopback, private and link-local addresses, so that those URLs
+/// can't be used to reach services on the server or its local network.
+/// Requests must be sent using `send`, which checks the initial URL as well.
+pub(crate) fn http_client() -> Result<Client, reqwest::Error> {
+    Client::builder()
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+        .user_agent(concat!("Enough/", env!("CARGO_PKG_VERSION")))
+        .redirect(Policy::custom(redirect_policy))
+        .dns_resolver(Arc::new(PublicResolver))
+        // A proxy would resolve host names itself, bypassing the filter.
+        .no_proxy()
+        .build()
+}
+
+/// Sends `request`, unless its URL is an IP address that isn't public.
+/// Returns `None` on failure.
+pub(crate) async fn send(request: RequestBuilder) -> Option<Response> {
+    let (client, request) = request.build_split();
+    let request = request.ok()?;
+
+    if !is_public_url(request.url()) {
+        return None;
+    }
+
+    client.execute(request).await.ok()
+}
+
+/// Reads the body of `response` as text, up to `MAX_RESPONSE_SIZE` bytes.
+pub(crate) async fn response_text(mut response: Response) -> Result<String, reqwest::Error> {
+    let mut body = Vec::new();
+
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+
+        if body.len() >= MAX_RESPONSE_SIZE {
+            body.truncate(MAX_RESPONSE_SIZE);
+            break;
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Decodes the entities that commonly occur in HTML attributes and titles.
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Returns the attributes of all `link` and `a` elements in `html`.
+fn link_attributes(html: &str) -> Vec<Vec<(String, String)>> {
+    let tag = Regex::new(r"(?i)<(?:link|a)\s[^>]*>").unwrap();
+    let attribute =
+        Regex::new(r#"([a-zA-Z-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap();
+
+    tag.find_iter(html)
+        .map(|tag| {
+            attribute
+                .captures_iter(tag.as_str())
+                .map(|captures| {
+                    let value = captures
+                        .get(2)
+                        .or_else(|| captures.get(3))
+                        .or_else(|| captures.get(4))
+                        .map_or("", |value| value.as_str());
+
+                    (captures[1].to_lowercase(), unescape_html(value))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn attribute<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(attribute_name, _)| attribute_name == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Returns the URLs linked to with the relation `rel` in the HTTP `Link` headers
+/// of `response`. Relative URLs are resolved against the URL after redirects.
+pub(crate) fn header_links(response: &Response, rel: &str) -> Vec<Url> {
+    let header_link = Regex::new(r#"<([^>]*)>\s*;\s*rel\s*=\s*"?([^";,]*)"?"#).unwrap();
+
+    response
+        .headers()
+        .get_all(LINK)
+        .iter()
+        .flat_map(|header| {
+            header_link
+                .captures_iter(header.to_str().unwrap_or_default())
+                .filter(|captures| captures[2].split_whitespace().any(|value| value == rel))
+                .filter_map(|captures| response.url().join(&captures[1]).ok())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Returns the URLs linked to with the relation `rel` by `link` and `a` elements
+/// in `html`, resolved against `url`.
+pub(crate) fn html_links(html: &str, url: &Url, rel: &str) -> Vec<Url> {
+    link_attributes(html)
+        .iter()
+        .filter_map(|attributes| {
+            let has_rel = attribute(attributes, "rel")?
+                .split_whitespace()
+                .any(|value| value == rel);
+
+            if has_rel {
+                url.join(attribute(attributes, "href")?).ok()
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Finds the Webmention endpoint of `target`, which is advertised
+/// either in an HTTP `Link` header, or in a `link` or `a` element.
+async fn discover_endpoint(client: &Client, target: &str) -> Option<Url> {
+    let response = send(client.get(target)).await?;
+
+    if let Some(endpoint) = header_links(&response, "webmention").into_iter().next() {
+        return Some(endpoint);
+    }
+
+    // Relative endpoint URLs are resolved against the URL after redirects.
+    let url = response.url().clone();
+
+    let html = response_text(response).await.ok()?;
+
+    html_links(&html, &url, "webmention").into_iter().next()
+}
+
+/// Returns the distinct absolute URLs linked to from `html`, except those on `base_url`.
+fn external_links(html: &str, base_url: &str) -> Vec<String> {
+    let mut links = Vec::new();
+
+    for attributes in link_attributes(html) {
+        if let Some(href) = attribute(&attributes, "href") {
+            if (href.starts_with("http://") || href.starts_with("https://"))
+                && !href.starts_with(base_url)
+                && !links.iter().any(|link| link == href)
+            {
+                links.push(href.to_owned());
+            }
+        }
+    }
+
+    links
+}
+
+/// Notifies all pages linked to from `post` that they have been mentioned.
+/// This happens in the background, and failures are ignored,
+/// because the post is published regardless. The site URL must be configured.
+pub(crate) async fn send_webmentions(
+    connection: &DatabaseConnection,
+    post: &page::Model,
+) -> Result<(), ErrorResponse> {
+    let base_url = settings(connection).await?.site_url;
+    let source = format!("{}/{}", base_url, post.url);
+    let targets = external_links(&post.content_html, &base_url);
+
+    if targets.is_empty() {
+        return Ok(());
+    }
+
+    tokio::spawn(async move {
+        let client = match http_client() {
+            Ok(client) => client,
+            Err(_) => return,
+        };
+
+        for target in targets {
+            if let Some(endpoint) = discover_endpoint(&client, &target).await {
+                send(
+                    client
+                        .post(endpoint)
+                        .form(&[("source", source.as_str()), ("target", target.as_str())]),
+                )
+                .await;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct WebmentionInput {
+    source: String,
+    target: String,
+}
+
+/// Receives a Webmention. Only the target is checked right away;
+/// the source is fetched and verified in the background.
+async fn post_webmention(
+    Extension(ref database_connection): Extension<DatabaseConnection>,
+    Form(webmention_input): Form<WebmentionInput>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    if !is_valid_site_url(&webmention_input.source) || !is_valid_site_url(&webmention_input.target)
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "invalid source or target, must be HTTP(S) URLs",
+        ));
+    }
+
+    if webmention_input.source == webmention_input.target {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "source and target must be different",
+        ));
+    }
+
+    let base_url = settings(database_connection).await?.site_url;
+
+    // Targets can only be checked against the site's public address,
+    // which the request's host might not be.
+    if base_url.is_empty() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "webmentions are not accepted until the site URL is configured",
+        ));
+    }
+
+    let target_error = (StatusCode::BAD_REQUEST, "target is not a post on this site");
+
+    let post_url = webmention_input
+        .target
+        .strip_prefix(&format!("{}/", base_url))
+        .map(|url| url.trim_end_matches('/'))
+        .ok_or(target_error)?;
+
+    let post = published_pages()
+        .filter(page::Column::IsPost.eq(true))
+        .filter(page::Column::Url.eq(post_url))
+        .one(database_connection)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "unable to retrieve post"))?
+        .ok_or(target_error)?;
+
+    let permit = VERIFICATIONS.try_acquire().map_err(|_| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "too many webmentions are being processed, please try again later",
+        )
+    })?;
+
+    let client = http_client().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to create HTTP client",
+        )
+    })?;
+
+    let database_connection = database_connection.clone();
+
+    tokio::spawn(async move {
+        // There is no one to report errors to at this point.
+        let _ = verify_webmention(
+            &database_connection,
+            &client,
+            post,
+            &webmention_input.source,
+            &webmention_input.target,
+        )
+        .await;
+
+        drop(permit);
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Stores the mention of `post` by `source` if `source` links to `target`,
+/// and deletes a previously stored mention if it no longer does.
+async fn verify_webmention(
+    connection: &DatabaseConnection,
+    client: &Client,
+    post: page::Model,
+    source: &str,
+    target: &str,
+) -> Result<(), ErrorResponse> {
+    let error = (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "unable to verify webmention",
+    );
+
+    let response = send(client.get(source)).await.ok_or(error)?;
+
+    let status = response.status();
+
+    let html = if status.is_success() {
+        response_text(response).await.map_err(|_| error)?
+    } else if status == StatusCode::GONE || status == StatusCode::NOT_FOUND {
+        String::new()
+    } else {
+        // The source might be temporarily unavailable, so nothing is changed.
+        return Err(error);
+    };
+
+    let links_to_target = link_attributes(&html)
+        .iter()
+        .any(|attributes| attribute(attributes, "href") == Some(target));
+
+    let existing_mention = post
+        .find_related(Webmention)
+        .filter(webmention::Column::Source.eq(source))
+        .one(connection)
+        .await
+        .map_err(|_| error)?;
+
+    if !links_to_target {
+        if let Some(mention) = existing_mention {
+            mention.delete(connection).await.map_err(|_| error)?;
+        }
+
+        return Ok(());
+    }
+
+    let title: String = Regex::new(r"(?is)<title[^>]*>(.*?)</title>")
+        .unwrap()
+        .captures(&html)
+        .map(|captures| unescape_html(captures[1].trim()))
+        .unwrap_or_default()
+        .chars()
+        .take(MAX_TITLE_LENGTH)
+        .collect();
+
+    let mut mention: webmention::ActiveModel = match existing_mention {
+        Some(mention) => {
+            // The title is shown with the mention, so a changed title must be approved again.
+            let is_approved = mention.is_approved && mention.title == title;
+
+            let mut mention: webmention::ActiveModel = mention.into();
+            mention.is_approved = Set(is_approved);
+            mention
+        }
+        None => webmention::ActiveModel {
+            page_id: Set(post.id),
+            source: Set(source.to_owned()),
+            is_approved: Set(false),
+            ..Default::default()
+        },
+    };
+
+    mention.title = Set(title);
+    mention.received = Set(Utc::now().naive_utc());
+
+    mention.save(connection).await.map_err(|_| error)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use axum::{response::Html, routing::get, Server};
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{Database, EntityTrait};
+
+    use super::*;
+
+    const TARGET: &str = "https://blog.example.com/a-post";
+
+    /// Starts a stand-in for other sites on a local port, and returns its URL.
+    /// It is addressed by host name, which the test client resolves without filtering.
+    async fn start_server() -> String {
+        // Links to the target until it has been fetched once.
+        let is_linked = Arc::new(AtomicBool::new(true));
+
+        let router = Router::new()
+            .route(
+                "/link-header",
+                get(|| async { ([(LINK, r#"</endpoint>; rel="webmention""#)], "") }),
+            )
+            .route(
+                "/link-header-multiple-rels",
+                get(|| async {
+                    (
+                        [(
+                            LINK,
+                            r#"</other>; rel="stylesheet", <https://endpoint.example/wm>; rel="webmention other""#,
+                        )],
+                        "",
+                    )
+                }),
+            )
+            .route(
+                "/directory/relative-href",
+                get(|| async {
+                    Html(r#"<html><head><link rel="webmention" href="../endpoint?relative=1"></head></html>"#)
+                }),
+            )
+            .route(
+                "/multiple-rels",
+                get(|| async {
+                    Html(r#"<a href="/other">Other</a> <a rel='nofollow webmention' href='/endpoint'>Endpoint</a>"#)
+                }),
+            )
+            .route("/no-endpoint", get(|| async { Html("<p>Nothing here</p>") }))
+            .route(
+                "/source-with-link",
+                get(|| async {
+                    Html(format!(
+                        "<title>Reply &amp; more</title><a href=\"{}\">A post</a>",
+                        TARGET,
+                    ))
+                }),
+            )
+            .route(
+                "/source-without-link",
+                get(|| async { Html("<title>Reply</title><p>No longer linked</p>") }),
+            )
+            .route(
+                "/source-edited",
+                get(move || async move {
+                    if is_linked.swap(false, Ordering::SeqCst) {
+                        Html(format!("<a href=\"{}\">A post</a>", TARGET))
+                    } else {
+                        Html("<p>Link removed</p>".to_owned())
+                    }
+                }),
+            )
+            .route("/source-gone", get(|| async { StatusCode::GONE }))
+            .route(
+                "/source-unavailable",
+                get(|| async { StatusCode::SERVICE_UNAVAILABLE }),
+            );
+
+        let server =
+            Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(router.into_make_service());
+
+        let port = server.local_addr().port();
+
+        tokio::spawn(server);
+
+        format!("http://localhost:{}", port)
+    }
+
+    async fn database_with_post() -> (DatabaseConnection, page::Model) {
+        let connection = Database::connect("sqlite::memory:").await.unwrap();
+
+        Migrator::up(&connection, None).await.unwrap();
+
+        let post = page::ActiveModel {
+            time: Set(Utc::now().naive_utc()),
+            title: Set("A post".to_owned()),
+            url: Set("a-post".to_owned()),
+            content_markdown: Set(String::new()),
+            content_html: Set(String::new()),
+            is_post: Set(true),
+            is_published: Set(true),
+            ..Default::default()
+        }
+        .insert(&connection)
+        .await
+        .unwrap();
+
+        (connection, post)
+    }
+
+    async fn mentions(connection: &DatabaseConnection) -> Vec<webmention::Model> {
+        Webmention::find().all(connection).await.unwrap()
+    }
+
+    #[test]
+    fn link_attributes_parses_quoting_styles() {
+        assert_eq!(
+            link_attributes(
+                r#"<p><A HREF="/a?x=1&amp;y=2" rel='me'>A</A><link rel=webmention href=/b><img src="c"></p>"#,
+            ),
+            vec![
+                vec![
+                    ("href".to_owned(), "/a?x=1&y=2".to_owned()),
+                    ("rel".to_owned(), "me".to_owned()),
+                ],
+                vec![
+                    ("rel".to_owned(), "webmention".to_owned()),
+                    ("href".to_owned(), "/b".to_owned()),
+                ],
+            ],
+        );
+    }
+
+    #[test]
+    fn external_links_skips_own_and_relative_links() {
+        assert_eq!(
+            external_links(
+                r#"<a href="https://other.example/1">1</a>
+                <a href="https://blog.example.com/own">Own</a>
+                <a href="/relative">Relative</a>
+                <a href="mailto:someone@example.com">Mail</a>
+                <a href="http://other.example/2">2</a>
+                <a href="https://other.example/1">1 again</a>"#,
+                "https://blog.example.com",
+            ),
+            vec!["https://other.example/1", "http://other.example/2"],
+        );
+    }
+
+    #[tokio::test]
+    async fn discover_endpoint_variants() {
+        let base_url = start_server().await;
+        let client = Client::new();
+
+        let endpoint = |path: &str| {
+            let client = client.clone();
+            let url = format!("{}{}", base_url, path);
+            async move { discover_endpoint(&client, &url).await.map(String::from) }
+        };
+
+        assert_eq!(
+            endpoint("/link-header").await,
+            Some(format!("{}/endpoint", base_url)),
+        );
+        assert_eq!(
+            endpoint("/link-header-multiple-rels").await.as_deref(),
+            Some("https://endpoint.example/wm"),
+        );
+        assert_eq!(
+            endpoint("/directory/relative-href").await,
+            Some(format!("{}/endpoint?relative=1", base_url)),
+        );
+        assert_eq!(
+            endpoint("/multiple-rels").await,
+            Some(format!("{}/endpoint", base_url)),
+        );
+        assert_eq!(endpoint("/no-endpoint").await, None);
+    }
+
+    #[tokio::test]
+    async fn verify_webmention_stores_and_removes_mentions() {
+        let base_url = start_server().await;
+        let client = Client::new();
+        let (connection, post) = database_with_post().await;
+
+        let source = format!("{}/source-with-link", base_url);
+
+        verify_webmention(&connection, &client, post.clone(), &source, TARGET)
+            .await
+            .unwrap();
+
+        let stored_mentions = mentions(&connection).await;
+        assert_eq!(stored_mentions.len(), 1);
+        assert_eq!(stored_mentions[0].source, source);
+        assert_eq!(stored_mentions[0].title, "Reply & more");
+        assert!(!stored_mentions[0].is_approved);
+
+        let mut mention: webmention::ActiveModel = stored_mentions[0].clone().into();
+        mention.is_approved = Set(true);
+        mention.update(&connection).await.unwrap();
+
+        // Verifying the same source again updates the existing mention,
+        // which stays approved as long as its title is unchanged.
+        verify_webmention(&connection, &client, post.clone(), &source, TARGET)
+            .await
+            .unwrap();
+
+        let stored_mentions = mentions(&connection).await;
+        assert_eq!(stored_mentions.len(), 1);
+        assert!(stored_mentions[0].is_approved);
+
+        // A changed title must be approved again.
+        let mut mention: webmention::ActiveModel = stored_mentions[0].clone().into();
+        mention.title = Set("Reply".to_owned());
+        mention.update(&connection).await.unwrap();
+
+        verify_webmention(&connection, &client, post.clone(), &source, TARGET)
+            .await
+            .unwrap();
+
+        let stored_mentions = mentions(&connection).await;
+        assert_eq!(stored_mentions.len(), 1);
+        assert_eq!(stored_mentions[0].title, "Reply & more");
+        assert!(!stored_mentions[0].is_approved);
+
+        // A source that doesn't link to the target is not stored.
+        let source_without_link = format!("{}/source-without-link", base_url);
+
+        verify_webmention(
+            &connection,
+            &client,
+            post.clone(),
+            &source_without_link,
+            TARGET,
+        )
+        .await
+        .unwrap();
+        assert_eq!(mentions(&connection).await.len(), 1);
+
+        // A temporarily unavailable source leaves the mention in place.
+        assert!(verify_webmention(
+            &connection,
+            &client,
+            post.clone(),
+            &format!("{}/source-unavailable", base_url),
+            TARGET,
+        )
+        .await
+        .is_err());
+        assert_eq!(mentions(&connection).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn verify_webmention_deletes_removed_link() {
+        let base_url = start_server().await;
+        let client = Client::new();
+        let (connection, post) = database_with_post().await;
+
+        let source = format!("{}/source-edited", base_url);
+
+        verify_webmention(&connection, &client, post.clone(), &source, TARGET)
+            .await
+            .unwrap();
+        assert_eq!(mentions(&connection).await.len(), 1);
+
+        verify_webmention(&connection, &client, post, &source, TARGET)
+            .await
+            .unwrap();
+        assert!(mentions(&connection).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn verify_webmention_deletes_gone_source() {
+        let base_url = start_server().await;
+        let client = Client::new();
+        let (connection, post) = database_with_post().await;
+
+        webmention::ActiveModel {
+            page_id: Set(post.id),
+            source: Set(format!("{}/source-gone", base_url)),
+            title: Set("Reply".to_owned()),
+            received: Set(Utc::now().naive_utc()),
+            is_approved: Set(true),
+            ..Default::default()
+        }
+        .insert(&connection)
+        .await
+        .unwrap();
+
+        verify_webmention(
+            &connection,
+            &client,
+            post,
+            &format!("{}/source-gone", base_url),
+            TARGET,
+        )
+        .await
+        .unwrap();
+        assert!(mentions(&connection).await.is_empty());
+    }
+
+    #[test]
+    fn is_public_address_rejects_internal_addresses() {
+        for address in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(!is_public_address(address.parse().unwrap()), "{}", address);
+        }
+
+        for address in ["93.184.216.34", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public_address(address.parse().unwrap()), "{}", address);
+        }
+    }
+
+    #[test]
+    fn is_public_url_checks_ip_addresses() {
+        let is_public = |url: &str| is_public_url(&Url::parse(url).unwrap());
+
+        assert!(is_public("https://example.com/"));
+        assert!(is_public("http://93.184.216.34/"));
+        assert!(!is_public("http://127.0.0.1:8080/"));
+        assert!(!is_public("http://[::1]/"));
+        assert!(!is_public("http://0x7f.1/"));
+        assert!(!is_public("ftp://example.com/"));
+    }
+}